@@ -0,0 +1,76 @@
+/*
+ * Regression test: a task extends a file with write(), while another
+ * task faults in the pages of a mapping of the same file. Previously,
+ * the page-fault handler deadlocked on the lock of the file content,
+ * if the writer was preempted while holding it.
+ */
+
+#include <stdio.h>
+#include <fcntl.h>
+#include <unistd.h>
+#include <sys/mman.h>
+
+#define PAGE_SIZE	4096
+#define PAGES		64
+#define ROUNDS		32
+#define CHUNK		(16 * PAGE_SIZE)
+
+static char buffer[CHUNK];
+
+int main(int argc, char** argv)
+{
+	int fd = open("/mmap_write", O_RDWR | O_CREAT, 0644);
+	if (fd < 0) {
+		printf("mmap_write: unable to create the file\n");
+		return 1;
+	}
+
+	for (int i = 0; i < CHUNK; i++)
+		buffer[i] = (char) (i / PAGE_SIZE);
+	for (int i = 0; i < PAGES * PAGE_SIZE / CHUNK; i++) {
+		if (write(fd, buffer, CHUNK) != CHUNK) {
+			printf("mmap_write: unable to initialize the file\n");
+			return 1;
+		}
+	}
+
+	pid_t pid = fork();
+	if (pid < 0) {
+		printf("mmap_write: fork failed\n");
+		return 1;
+	}
+
+	if (pid == 0) {
+		/* the child extends the file, its file position is shared with the parent */
+		for (int i = 0; i < ROUNDS * 4; i++) {
+			if (write(fd, buffer, CHUNK) != CHUNK) {
+				printf("mmap_write: write failed\n");
+				return 1;
+			}
+		}
+
+		return 0;
+	}
+
+	/* the parent faults in fresh pages of the beginning of the file */
+	for (int round = 0; round < ROUNDS; round++) {
+		char* p = mmap(NULL, PAGES * PAGE_SIZE, PROT_READ, MAP_PRIVATE, fd, 0);
+		if (p == MAP_FAILED) {
+			printf("mmap_write: mmap failed\n");
+			return 1;
+		}
+
+		for (int i = 0; i < PAGES; i++) {
+			if (p[i * PAGE_SIZE] != (char) (i % (CHUNK / PAGE_SIZE))) {
+				printf("mmap_write: invalid content of page %d\n", i);
+				return 1;
+			}
+		}
+
+		munmap(p, PAGES * PAGE_SIZE);
+	}
+
+	printf("mmap_write: passed\n");
+
+	return 0;
+}
@@ -8,11 +8,11 @@
 // Export our platform-specific modules.
 #[cfg(target_arch="x86_64")]
 pub use self::x86_64::kernel::{serial,processor,irq,init,jump_to_user_land,register_task,
-	get_memory_size,get_memfile};
+	get_memory_size,get_memfile,get_monotonic_ns,get_boot_time};
 
 // Export our platform-specific modules.
 #[cfg(target_arch="x86_64")]
-pub use self::x86_64::load_application;
+pub use self::x86_64::{load_application,execve};
 
 // Export our platform-specific modules.
 #[cfg(target_arch="x86_64")]
@@ -24,7 +24,7 @@ pub use self::x86_64::mm;
 
 // Export our platform-specific modules.
 #[cfg(target_arch="x86_64")]
-pub use self::x86_64::mm::paging::{get_kernel_root_page_table,drop_user_space,PageSize,BasePageSize};
+pub use self::x86_64::mm::paging::{get_kernel_root_page_table,drop_user_space,AddressSpace,PageSize,BasePageSize};
 
 // Implementations for x86_64.
 #[cfg(target_arch="x86_64")]
@@ -10,8 +10,12 @@
 use core::fmt;
 use logging::*;
 use scheduler::*;
+use time;
+use arch::x86_64::kernel::vdso;
+use arch::x86_64::kernel::processor::{clac,clear_task_switched_flag};
 use synch::spinlock::*;
-use arch::x86_64::mm::paging::page_fault_handler;
+use arch::x86_64::mm::paging::{page_fault_handler, BasePageSize, PageSize};
+use x86::controlregs;
 use x86::dtables::{DescriptorTablePointer,lidt};
 use x86::Ring;
 use x86::bits64::paging::VAddr;
@@ -155,12 +159,19 @@ extern "x86-interrupt" fn invalid_opcode_exception(stack_frame: &mut ExceptionSt
 	abort();
 }
 
-extern "x86-interrupt" fn no_coprocessor_exception(stack_frame: &mut ExceptionStackFrame)
+extern "x86-interrupt" fn no_coprocessor_exception(_stack_frame: &mut ExceptionStackFrame)
 {
-	info!("Task {} receive a Coprocessor Not Available Exception: {:#?}",
-		get_current_taskid(), stack_frame);
-	send_eoi_to_master();
-	abort();
+	// the interrupted code may have opened a user-access window
+	// => close it, iretq restores the AC flag
+	clac();
+
+	// The task switched flag is set after each task switch.
+	// => the current task uses the FPU for the first time since
+	// its last activation and we have to load its FPU state
+	debug!("Task {} receive a Coprocessor Not Available Exception", get_current_taskid());
+
+	clear_task_switched_flag();
+	fpu_switch();
 }
 
 // 8: Double Fault Exception (With Error Code!)
@@ -168,6 +179,14 @@ extern "x86-interrupt" fn no_coprocessor_exception(stack_frame: &mut ExceptionSt
 extern "x86-interrupt" fn double_fault_exception(stack_frame: &mut ExceptionStackFrame,
 	error_code: u64)
 {
+	// a page fault in the guard page of the kernel stack can't be
+	// delivered on the same stack and escalates to a double fault
+	let address = unsafe { controlregs::cr2() };
+	let stack = get_current_stack();
+	if address < stack && address >= stack - BasePageSize::SIZE {
+		error!("Kernel stack overflow of task {} at {:#X}", get_current_taskid(), address);
+	}
+
 	info!("Task {} receive a Double Fault Exception: {:#?}, error_code {}",
 		get_current_taskid(), stack_frame, error_code);
 	send_eoi_to_master();
@@ -277,9 +296,15 @@ extern "x86-interrupt" fn unhandled_irq2(stack_frame: &mut ExceptionStackFrame)
 
 extern "x86-interrupt" fn timer_handler(stack_frame: &mut ExceptionStackFrame)
 {
+	// the interrupted code may have opened a user-access window
+	// => close it, iretq restores the AC flag
+	clac();
+
 	debug!("Task {} receive timer interrupt!\n{:#?}", get_current_taskid(), stack_frame);
 
 	send_eoi_to_master();
+	time::tick();
+	vdso::update();
 	schedule();
 }
 
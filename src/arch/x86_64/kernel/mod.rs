@@ -11,14 +11,20 @@ pub mod task;
 pub mod irq;
 pub mod switch;
 mod gdt;
+mod multiboot;
 mod pit;
+mod rtc;
 mod start;
 mod syscall;
+pub mod vdso;
 
 use core::ptr::read_volatile;
 use consts::*;
 use logging::*;
 pub use arch::x86_64::kernel::syscall::syscall_handler;
+pub use arch::x86_64::kernel::multiboot::{get_memory_map,MemoryRegion,MemoryRegionKind};
+pub use arch::x86_64::kernel::pit::{get_monotonic_ns,get_tsc_frequency};
+pub use arch::x86_64::kernel::rtc::get_boot_time;
 
 #[repr(C)]
 struct KernelHeader {
@@ -31,8 +37,11 @@ struct KernelHeader {
 }
 
 /// Kernel header to announce machine features
+///
+/// The header is initialized by ehyve or, if the kernel is booted
+/// by a Multiboot compliant boot loader, by `multiboot::init`.
 #[link_section = ".kheader"]
-static KERNEL_HEADER: KernelHeader = KernelHeader {
+static mut KERNEL_HEADER: KernelHeader = KernelHeader {
 	magic_number: 0xDEADC0DEu32,
 	version: 0,
 	mem_limit: 0,
@@ -65,11 +74,10 @@ pub fn register_task() {
 
 #[inline(never)]
 #[naked]
-pub fn jump_to_user_land(entry: u64) -> !
+pub fn jump_to_user_land(entry: u64, stack: u64) -> !
 {
 	let ds = 0x23u64;
 	let cs = 0x2bu64;
-	let stack = USER_STACK - 0x100;
 
 	debug!("Set user space stack to 0x{:x}", stack);
 
@@ -199,8 +207,11 @@ pub fn syscall6(arg0: u64,
 
 /// Initialize module, must be called once, and only once
 pub fn init() {
+	multiboot::init();
 	processor::init();
 	gdt::init();
 	irq::init();
 	pit::init();
+	rtc::init();
+	vdso::init();
 }
@@ -0,0 +1,319 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Support of Multiboot and Multiboot2 compliant boot loaders
+//!
+//! The trampoline in `start.rs` stores the magic number and the address of the
+//! boot information, which are translated into the kernel header. Afterwards, the
+//! kernel sees the same information as if it was started by ehyve.
+
+use core::ptr;
+use arch::x86_64::mm::paging::{BasePageSize,LargePageSize,PageSize};
+use logging::*;
+
+/// Magic number passed by a Multiboot compliant boot loader
+const MULTIBOOT_BOOTLOADER_MAGIC: u32 = 0x2BADB002;
+/// Magic number passed by a Multiboot2 compliant boot loader
+const MULTIBOOT2_BOOTLOADER_MAGIC: u32 = 0x36D76289;
+
+// flags of the Multiboot information structure
+const MULTIBOOT_INFO_MEMORY: u32 = (1 << 0);
+const MULTIBOOT_INFO_MODS: u32 = (1 << 3);
+const MULTIBOOT_INFO_MEM_MAP: u32 = (1 << 6);
+
+// tag types of the Multiboot2 information structure
+const MULTIBOOT2_TAG_END: u32 = 0;
+const MULTIBOOT2_TAG_MODULE: u32 = 3;
+const MULTIBOOT2_TAG_BASIC_MEMINFO: u32 = 4;
+const MULTIBOOT2_TAG_MMAP: u32 = 6;
+
+// types of the memory regions in the memory map
+const MULTIBOOT_MEMORY_AVAILABLE: u32 = 1;
+const MULTIBOOT_MEMORY_RESERVED: u32 = 2;
+const MULTIBOOT_MEMORY_ACPI_RECLAIMABLE: u32 = 3;
+const MULTIBOOT_MEMORY_NVS: u32 = 4;
+const MULTIBOOT_MEMORY_BADRAM: u32 = 5;
+
+/// Maximum number of entries in the memory map
+const MAX_MEMORY_REGIONS: usize = 64;
+
+/// Type of a memory region as reported by the firmware
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryRegionKind {
+	/// Memory, which is available for general use
+	Available,
+	/// Memory, which is reserved by the firmware (e.g. MMIO)
+	Reserved,
+	/// Memory, which holds ACPI tables
+	AcpiReclaimable,
+	/// Memory, which has to be preserved on hibernation
+	AcpiNvs,
+	/// Defective memory
+	BadMemory
+}
+
+impl MemoryRegionKind {
+	fn from(mem_type: u32) -> Self {
+		match mem_type {
+			MULTIBOOT_MEMORY_AVAILABLE => MemoryRegionKind::Available,
+			MULTIBOOT_MEMORY_ACPI_RECLAIMABLE => MemoryRegionKind::AcpiReclaimable,
+			MULTIBOOT_MEMORY_NVS => MemoryRegionKind::AcpiNvs,
+			MULTIBOOT_MEMORY_BADRAM => MemoryRegionKind::BadMemory,
+			MULTIBOOT_MEMORY_RESERVED | _ => MemoryRegionKind::Reserved
+		}
+	}
+}
+
+/// An entry of the memory map
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryRegion {
+	/// Physical start address of the region
+	pub start: usize,
+	/// Physical address of the first byte behind the region
+	pub end: usize,
+	/// Type of the region
+	pub kind: MemoryRegionKind
+}
+
+impl MemoryRegion {
+	const fn empty() -> Self {
+		MemoryRegion {
+			start: 0,
+			end: 0,
+			kind: MemoryRegionKind::Reserved
+		}
+	}
+}
+
+/// Memory map of the firmware, sorted by the start addresses
+static mut MEMORY_MAP: [MemoryRegion; MAX_MEMORY_REGIONS] = [MemoryRegion::empty(); MAX_MEMORY_REGIONS];
+static mut MEMORY_MAP_ENTRIES: usize = 0;
+
+/// Magic number in eax, stored by the trampoline
+#[no_mangle]
+static mut MBOOT_MAGIC: u32 = 0;
+
+/// Physical address of the boot information in ebx, stored by the trampoline
+#[no_mangle]
+static mut MBOOT_INFO: u32 = 0;
+
+extern "C" {
+	static kernel_start: u8;
+	static kernel_end: u8;
+}
+
+#[inline]
+unsafe fn read<T>(addr: usize) -> T {
+	ptr::read_unaligned(addr as *const T)
+}
+
+/// Call `f` for each entry of the memory map from a Multiboot boot loader
+unsafe fn mb1_memory_map(info: usize, f: &mut FnMut(u64, u64, u32)) {
+	let flags: u32 = read(info);
+
+	if flags & MULTIBOOT_INFO_MEM_MAP != 0 {
+		let length: u32 = read(info + 44);
+		let addr: u32 = read(info + 48);
+		let mut entry = addr as usize;
+
+		while entry < (addr + length) as usize {
+			// the size field doesn't include the field itself
+			let size: u32 = read(entry);
+			f(read(entry + 4), read(entry + 12), read(entry + 20));
+			entry += size as usize + 4;
+		}
+	} else if flags & MULTIBOOT_INFO_MEMORY != 0 {
+		// mem_upper is the amount of memory above 1 MiB in KiB
+		let mem_upper: u32 = read(info + 8);
+		f(0x100000, (mem_upper as u64) << 10, MULTIBOOT_MEMORY_AVAILABLE);
+	}
+}
+
+/// Returns the first boot module of a Multiboot boot loader
+unsafe fn mb1_module(info: usize) -> Option<(u64, u64)> {
+	let flags: u32 = read(info);
+
+	if flags & MULTIBOOT_INFO_MODS != 0 {
+		let count: u32 = read(info + 20);
+		let addr: u32 = read(info + 24);
+
+		if count > 0 {
+			let start: u32 = read(addr as usize);
+			let end: u32 = read(addr as usize + 4);
+			return Some((start as u64, end as u64));
+		}
+	}
+
+	None
+}
+
+/// Call `f` for each tag of the Multiboot2 information structure
+unsafe fn mb2_tags(info: usize, f: &mut FnMut(u32, usize)) {
+	// skip total_size and reserved
+	let mut tag = info + 8;
+
+	loop {
+		let tag_type: u32 = read(tag);
+		let tag_size: u32 = read(tag + 4);
+
+		if tag_type == MULTIBOOT2_TAG_END {
+			break;
+		}
+
+		f(tag_type, tag);
+
+		// tags are 8-byte aligned
+		tag = align_up!(tag + tag_size as usize, 8);
+	}
+}
+
+/// Call `f` for each entry of the memory map from a Multiboot2 boot loader
+unsafe fn mb2_memory_map(info: usize, f: &mut FnMut(u64, u64, u32)) {
+	let mut found_mmap = false;
+
+	mb2_tags(info, &mut |tag_type, tag| {
+		if tag_type == MULTIBOOT2_TAG_MMAP {
+			let tag_size: u32 = read(tag + 4);
+			let entry_size: u32 = read(tag + 8);
+			let mut entry = tag + 16;
+
+			while entry < tag + tag_size as usize {
+				f(read(entry), read(entry + 8), read(entry + 16));
+				entry += entry_size as usize;
+			}
+
+			found_mmap = true;
+		}
+	});
+
+	if found_mmap == false {
+		mb2_tags(info, &mut |tag_type, tag| {
+			if tag_type == MULTIBOOT2_TAG_BASIC_MEMINFO {
+				let mem_upper: u32 = read(tag + 12);
+				f(0x100000, (mem_upper as u64) << 10, MULTIBOOT_MEMORY_AVAILABLE);
+			}
+		});
+	}
+}
+
+/// Returns the first boot module of a Multiboot2 boot loader
+unsafe fn mb2_module(info: usize) -> Option<(u64, u64)> {
+	let mut module = None;
+
+	mb2_tags(info, &mut |tag_type, tag| {
+		if tag_type == MULTIBOOT2_TAG_MODULE && module.is_none() {
+			let start: u32 = read(tag + 8);
+			let end: u32 = read(tag + 12);
+			module = Some((start as u64, end as u64));
+		}
+	});
+
+	module
+}
+
+/// Returns the memory map of the firmware. The map is empty, if
+/// the kernel is started by ehyve.
+pub fn get_memory_map() -> &'static [MemoryRegion] {
+	unsafe { &MEMORY_MAP[0..MEMORY_MAP_ENTRIES] }
+}
+
+/// Evaluate the boot information of a Multiboot compliant boot loader
+/// and initialize the kernel header respectively. Does nothing, if the
+/// kernel is started by ehyve.
+pub fn init() {
+	let magic = unsafe { MBOOT_MAGIC };
+	let info = unsafe { MBOOT_INFO } as usize;
+
+	let (memory_map, module): (unsafe fn(usize, &mut FnMut(u64, u64, u32)), _) = match magic {
+		MULTIBOOT_BOOTLOADER_MAGIC => {
+			info!("Booted by a Multiboot compliant boot loader");
+			(mb1_memory_map, unsafe { mb1_module(info) })
+		},
+		MULTIBOOT2_BOOTLOADER_MAGIC => {
+			info!("Booted by a Multiboot2 compliant boot loader");
+			(mb2_memory_map, unsafe { mb2_module(info) })
+		},
+		_ => return
+	};
+
+	let kstart = unsafe { &kernel_start as *const u8 as u64 };
+	let kend = align_up!(unsafe { &kernel_end as *const u8 as usize }, LargePageSize::SIZE) as u64;
+
+	// Store the memory map before we touch the memory and determine
+	// the end of the available region, which includes the kernel.
+	let mut kernel_region_end: u64 = 0;
+	let mut mem_limit: u64 = 0;
+	unsafe {
+		memory_map(info, &mut |base, length, mem_type| {
+			if length == 0 {
+				return;
+			}
+
+			if mem_type == MULTIBOOT_MEMORY_AVAILABLE {
+				if base <= kstart && base + length > kend {
+					kernel_region_end = base + length;
+				}
+				if base + length > mem_limit {
+					mem_limit = base + length;
+				}
+			}
+
+			if MEMORY_MAP_ENTRIES < MAX_MEMORY_REGIONS {
+				// insertion sort by the start address
+				let region = MemoryRegion {
+					start: base as usize,
+					end: (base + length) as usize,
+					kind: MemoryRegionKind::from(mem_type)
+				};
+				let mut i = MEMORY_MAP_ENTRIES;
+				while i > 0 && MEMORY_MAP[i-1].start > region.start {
+					MEMORY_MAP[i] = MEMORY_MAP[i-1];
+					i -= 1;
+				}
+				MEMORY_MAP[i] = region;
+				MEMORY_MAP_ENTRIES += 1;
+			} else {
+				info!("Ignore memory region 0x{:x} - 0x{:x}, memory map is full", base, base + length);
+			}
+		});
+	}
+
+	if kernel_region_end == 0 {
+		panic!("Unable to find the memory region of the kernel");
+	}
+
+	let (mut file_addr, mut file_length) = (0u64, 0u64);
+	if let Some((start, end)) = module {
+		// The boot loader places the module somewhere in the free memory.
+		// ehyve puts the file at the end of the memory and consequently,
+		// we move the module to the end of the available memory region.
+		// The trampoline identity-maps the first 4 GiB and both regions
+		// are accessible.
+		let length = end - start;
+		let dest = align_down!(kernel_region_end - length, BasePageSize::SIZE as u64);
+
+		if dest < kend {
+			panic!("Boot module (0x{:x} - 0x{:x}) doesn't fit into memory", start, end);
+		}
+
+		debug!("Move boot module from 0x{:x} to 0x{:x} (length 0x{:x})", start, dest, length);
+		unsafe { ptr::copy(start as *const u8, dest as *mut u8, length as usize); }
+
+		file_addr = dest;
+		file_length = length;
+		if mem_limit == kernel_region_end {
+			mem_limit = dest;
+		}
+	}
+
+	unsafe {
+		super::KERNEL_HEADER.mem_limit = mem_limit;
+		super::KERNEL_HEADER.file_addr = file_addr;
+		super::KERNEL_HEADER.file_length = file_length;
+		super::KERNEL_HEADER.version = 1;
+	}
+}
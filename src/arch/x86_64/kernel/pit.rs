@@ -13,6 +13,15 @@ use x86::time::rdtsc;
 
 const CLOCK_TICK_RATE: u32 = 1193182u32; /* 8254 chip's internal oscillator frequency */
 
+/// Duration of the TSC calibration in milliseconds
+const CALIBRATION_MS: u64 = 10;
+
+/// Frequency of the time stamp counter in Hz, zero if the calibration failed
+static mut TSC_FREQUENCY: u64 = 0;
+
+/// Value of the time stamp counter at the end of the calibration
+static mut TSC_START: u64 = 0;
+
 unsafe fn wait_some_time() {
  	let start = rdtsc();
 
@@ -22,11 +31,74 @@ unsafe fn wait_some_time() {
 	}
 }
 
+/// Measure the frequency of the time stamp counter. Channel 2 of the PIT
+/// counts down for `CALIBRATION_MS` and the elapsed TSC cycles are compared.
+unsafe fn calibrate_tsc() -> u64 {
+	let latch = (CLOCK_TICK_RATE as u64 * CALIBRATION_MS / 1000) as u16;
+
+	// enable the gate of channel 2 and disable the speaker
+	outb(0x61, (inb(0x61) & !0x02) | 0x01);
+
+	/*
+	 * 0xB0 means channel 2, write low- and then high-byte,
+	 * mode 0 "interrupt on terminal count", binary counter
+	 */
+	outb(0x43, 0xB0);
+	outb(0x42, (latch & 0xFF) as u8);
+	outb(0x42, (latch >> 8) as u8);
+
+	let start = rdtsc();
+
+	// bit 5 of port 0x61 signals the terminal count of channel 2
+	while inb(0x61) & 0x20 == 0 {
+		// A missing PIT (e.g. in a minimal hypervisor) never reaches the terminal count.
+		// 2^32 cycles correspond to 10 ms at a TSC frequency of more than 400 GHz.
+		if rdtsc().wrapping_sub(start) > 1 << 32 {
+			return 0;
+		}
+	}
+
+	rdtsc().wrapping_sub(start) * 1000 / CALIBRATION_MS
+}
+
+/// Returns the frequency of the time stamp counter in Hz or zero, if it is unknown
+pub fn get_tsc_frequency() -> u64 {
+	unsafe { TSC_FREQUENCY }
+}
+
+/// Returns the value of the time stamp counter at the end of the calibration
+pub fn get_tsc_start() -> u64 {
+	unsafe { TSC_START }
+}
+
+/// Returns the nanoseconds since the TSC calibration
+pub fn get_monotonic_ns() -> Option<u64> {
+	let frequency = get_tsc_frequency();
+
+	if frequency == 0 {
+		return None;
+	}
+
+	let cycles = unsafe { rdtsc().wrapping_sub(TSC_START) };
+	Some((cycles as u128 * 1_000_000_000 / frequency as u128) as u64)
+}
+
 // initialize the Programmable Interrupt controller
 pub fn init()
 {
 	debug!("initialize timer");
 
+	unsafe {
+		TSC_FREQUENCY = calibrate_tsc();
+		TSC_START = rdtsc();
+	}
+
+	if get_tsc_frequency() > 0 {
+		info!("TSC frequency {} MHz", get_tsc_frequency() / 1_000_000);
+	} else {
+		error!("Unable to calibrate the TSC, the clock has the resolution of the timer interrupt");
+	}
+
 	let latch = ((CLOCK_TICK_RATE + TIMER_FREQ/2) / TIMER_FREQ) as u16;
 
 	unsafe {
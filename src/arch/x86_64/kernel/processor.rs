@@ -5,6 +5,7 @@ use x86::controlregs::*;
 use x86::msr::*;
 use x86::io::*;
 use x86::cpuid::*;
+use x86::time::rdtsc;
 use arch::x86_64::kernel::syscall_handler;
 use scheduler::task::BOOT_STACK;
 
@@ -18,9 +19,133 @@ const EFER_LMSLE: u64 = (1 << 13);
 const EFER_FFXSR: u64 = (1 << 14);
 const EFER_TCE: u64 = (1 << 15);
 
+// Bits of the extended control register XCR0
+const XCR0_X87: u64 = (1 << 0);
+const XCR0_SSE: u64 = (1 << 1);
+const XCR0_AVX: u64 = (1 << 2);
+
 static mut PHYSICAL_ADDRESS_BITS: u8 = 0;
 static mut LINEAR_ADDRESS_BITS: u8 = 0;
 static mut SUPPORTS_1GIB_PAGES: bool = false;
+static mut SUPPORTS_XSAVE: bool = false;
+static mut SUPPORTS_RDRAND: bool = false;
+static mut SUPPORTS_SMAP: bool = false;
+/// State of the fallback random number generator
+static mut RANDOM_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+/// State components, which are saved and restored by xsave / xrstor
+static mut XSAVE_MASK: u64 = 0;
+
+/// Legacy region of the FXSAVE / XSAVE area
+///
+/// See Intel Vol. 1, Table 10-2 "Format of an FXSAVE Area"
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct FxsaveArea {
+	control_word: u16,
+	status_word: u16,
+	tag_word: u16,
+	last_opcode: u16,
+	last_instruction_pointer: u64,
+	last_data_pointer: u64,
+	mxcsr: u32,
+	mxcsr_mask: u32,
+	st_space: [u8; 8*16],
+	xmm_space: [u8; 16*16],
+	padding: [u8; 96]
+}
+
+/// Header of the XSAVE area
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct XsaveHeader {
+	xstate_bv: u64,
+	xcomp_bv: u64,
+	reserved: [u64; 6]
+}
+
+/// Upper halves of the registers YMM0-YMM15
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct XsaveAvxState {
+	ymmh_space: [u8; 16*16]
+}
+
+/// Memory area to store the state of the floating point unit (x87, SSE and AVX)
+///
+/// The layout is compatible to the FXSAVE and to the (non-compacted) XSAVE
+/// format. We enable at most the x87, SSE and AVX components in XCR0 and
+/// consequently, the area has a fixed size of 832 bytes.
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+pub struct FPUState {
+	legacy_region: FxsaveArea,
+	header: XsaveHeader,
+	avx_state: XsaveAvxState
+}
+
+impl FPUState {
+	/// Creates the initial FPU state of a task
+	pub const fn new() -> Self {
+		FPUState {
+			legacy_region: FxsaveArea {
+				// mask all x87 exceptions, 64 bit precision, round to nearest
+				control_word: 0x37f,
+				status_word: 0,
+				// all registers are empty (abridged format)
+				tag_word: 0,
+				last_opcode: 0,
+				last_instruction_pointer: 0,
+				last_data_pointer: 0,
+				// mask all SSE exceptions, round to nearest
+				mxcsr: 0x1f80,
+				mxcsr_mask: 0,
+				st_space: [0; 8*16],
+				xmm_space: [0; 16*16],
+				padding: [0; 96]
+			},
+			header: XsaveHeader {
+				xstate_bv: 0,
+				xcomp_bv: 0,
+				reserved: [0; 6]
+			},
+			avx_state: XsaveAvxState {
+				ymmh_space: [0; 16*16]
+			}
+		}
+	}
+
+	/// Load the FPU state from memory into the FPU registers
+	pub fn restore(&self) {
+		if supports_xsave() {
+			let bitmask: u32 = unsafe { XSAVE_MASK } as u32;
+
+			unsafe {
+				asm!("xrstorq $0" :: "*m"(self as *const Self), "{eax}"(bitmask), "{edx}"(bitmask)
+					:: "volatile");
+			}
+		} else {
+			unsafe {
+				asm!("fxrstorq $0" :: "*m"(self as *const Self) :: "volatile");
+			}
+		}
+	}
+
+	/// Store the current FPU registers into memory
+	pub fn save(&mut self) {
+		if supports_xsave() {
+			let bitmask: u32 = unsafe { XSAVE_MASK } as u32;
+
+			unsafe {
+				asm!("xsaveq $0" : "=*m"(self as *mut Self) : "{eax}"(bitmask), "{edx}"(bitmask)
+					: "memory" : "volatile");
+			}
+		} else {
+			unsafe {
+				asm!("fxsaveq $0; fnclex" : "=*m"(self as *mut Self) :: "memory" : "volatile");
+			}
+		}
+	}
+}
 
 /// Force strict CPU ordering, serializes load and store operations.
 #[inline(always)]
@@ -85,6 +210,125 @@ pub fn supports_1gib_pages() -> bool {
 	unsafe { SUPPORTS_1GIB_PAGES }
 }
 
+pub fn supports_xsave() -> bool {
+	unsafe { SUPPORTS_XSAVE }
+}
+
+pub fn supports_rdrand() -> bool {
+	unsafe { SUPPORTS_RDRAND }
+}
+
+pub fn supports_smap() -> bool {
+	unsafe { SUPPORTS_SMAP }
+}
+
+/// Allow the kernel to access user-space pages by setting the AC flag
+#[inline(always)]
+pub fn stac() {
+	if supports_smap() {
+		unsafe { asm!("stac" ::: "cc" : "volatile"); }
+	}
+}
+
+/// Forbid the kernel to access user-space pages by clearing the AC flag
+#[inline(always)]
+pub fn clac() {
+	if supports_smap() {
+		unsafe { asm!("clac" ::: "cc" : "volatile"); }
+	}
+}
+
+/// Window, in which the kernel is allowed to access the user space.
+/// With SMAP, every other access of the kernel to a user-space page
+/// raises a page fault. Windows may be nested, the access is only
+/// forbidden again, if the outermost window is dropped.
+pub struct UserAccess {
+	/// AC flag before opening the window
+	ac: bool
+}
+
+impl UserAccess {
+	pub fn new() -> Self {
+		let rflags: u64;
+
+		unsafe { asm!("pushfq; pop $0" : "=r"(rflags) ::: "volatile"); }
+		stac();
+
+		UserAccess { ac: rflags & (1 << 18) != 0 }
+	}
+}
+
+impl Drop for UserAccess {
+	fn drop(&mut self) {
+		if !self.ac {
+			clac();
+		}
+	}
+}
+
+/// Returns a random number. If the CPU doesn't support rdrand, the number
+/// is derived from the time stamp counter and isn't suitable for cryptography.
+pub fn get_random() -> u64 {
+	if supports_rdrand() {
+		// rdrand may fail, if the entropy is exhausted
+		for _i in 0..10 {
+			let value: u64;
+			let success: u8;
+
+			unsafe { asm!("rdrand $0; setc $1" : "=r"(value), "=r"(success) :: "cc" : "volatile"); }
+
+			if success != 0 {
+				return value;
+			}
+		}
+	}
+
+	// xorshift64 seeded with the time stamp counter
+	unsafe {
+		let mut x = RANDOM_SEED ^ rdtsc();
+		x ^= x << 13;
+		x ^= x >> 7;
+		x ^= x << 17;
+		RANDOM_SEED = x;
+
+		x
+	}
+}
+
+/// Clear the task switched flag in CR0, which allows the usage
+/// of the FPU without raising an exception
+#[inline(always)]
+pub fn clear_task_switched_flag() {
+	unsafe {
+		asm!("clts" :::: "volatile");
+	}
+}
+
+/// Returns the FS base of the current task
+#[inline(always)]
+pub fn readfs() -> usize {
+	unsafe { rdmsr(IA32_FS_BASE) as usize }
+}
+
+/// Set the FS base of the current task
+#[inline(always)]
+pub fn writefs(fs: usize) {
+	unsafe { wrmsr(IA32_FS_BASE, fs as u64); }
+}
+
+/// Returns the user-level GS base of the current task. Within the kernel,
+/// the user-level GS base is inactive and swapped in by swapgs.
+#[inline(always)]
+pub fn read_user_gs() -> usize {
+	unsafe { rdmsr(IA32_KERNEL_GS_BASE) as usize }
+}
+
+/// Set the user-level GS base of the current task
+#[inline(always)]
+pub fn write_user_gs(gs: usize) {
+	unsafe { wrmsr(IA32_KERNEL_GS_BASE, gs as u64); }
+}
+
 pub fn get_linear_address_bits() -> u8 {
 	unsafe { LINEAR_ADDRESS_BITS }
 }
@@ -104,6 +348,10 @@ pub fn init() {
 	cr0 = cr0 | Cr0::CR0_ALIGNMENT_MASK;
 	cr0 = cr0 | Cr0::CR0_NUMERIC_ERROR;
 	cr0 = cr0 | Cr0::CR0_MONITOR_COPROCESSOR;
+	// write protection is also required in ring 0 to detect copy-on-write pages
+	cr0 = cr0 | Cr0::CR0_WRITE_PROTECT;
+	// a boot loader may leave the x87 emulation enabled
+	cr0 = cr0 & !Cr0::CR0_EMULATE_COPROCESSOR;
 	// enable cache
 	cr0 = cr0 & !(Cr0::CR0_CACHE_DISABLE|Cr0::CR0_NOT_WRITE_THROUGH);
 
@@ -133,6 +381,22 @@ pub fn init() {
 		panic!("eduOS-rs requires the CPU feature FSGSBASE");
 	}
 
+	let (has_smep, has_smap) = match cpuid.get_extended_feature_info() {
+		Some(efinfo) => (efinfo.has_smep(), efinfo.has_smap()),
+		None => (false, false)
+	};
+
+	if has_smep {
+		info!("Enable supervisor mode execution prevention (SMEP)");
+		cr4 |= Cr4::CR4_ENABLE_SMEP;
+	}
+
+	if has_smap {
+		info!("Enable supervisor mode access prevention (SMAP)");
+		cr4 |= Cr4::CR4_ENABLE_SMAP;
+		unsafe { SUPPORTS_SMAP = true; }
+	}
+
 	let has_mce = match cpuid.get_feature_info() {
 		Some(finfo) => finfo.has_mce(),
 		None => false
@@ -142,6 +406,34 @@ pub fn init() {
 		cr4 |= Cr4::CR4_ENABLE_MACHINE_CHECK; // enable machine check exceptions
 	}
 
+	let has_sse = match cpuid.get_feature_info() {
+		Some(finfo) => finfo.has_fxsave_fxstor() && finfo.has_sse(),
+		None => false
+	};
+
+	if has_sse {
+		// enable fxsave/fxrstor and unmasked SSE exceptions
+		cr4 |= Cr4::CR4_ENABLE_SSE | Cr4::CR4_UNMASKED_SSE;
+	} else {
+		panic!("eduOS-rs requires the CPU features FXSR and SSE");
+	}
+
+	let (has_xsave, has_avx) = match cpuid.get_feature_info() {
+		Some(finfo) => (finfo.has_xsave(), finfo.has_avx()),
+		None => (false, false)
+	};
+
+	if has_xsave {
+		cr4 |= Cr4::CR4_ENABLE_OS_XSAVE;
+	}
+
+	unsafe {
+		SUPPORTS_RDRAND = match cpuid.get_feature_info() {
+			Some(finfo) => finfo.has_rdrand(),
+			None => false
+		};
+	}
+
 	// disable performance monitoring counter
 	// allow the usage of rdtsc in user space
 	cr4 &= !(Cr4::CR4_ENABLE_PPMC|Cr4::CR4_TIME_STAMP_DISABLE);
@@ -150,6 +442,26 @@ pub fn init() {
 
 	unsafe { cr4_write(cr4) };
 
+	if has_xsave {
+		let mut xcr0 = XCR0_X87 | XCR0_SSE;
+		if has_avx {
+			xcr0 |= XCR0_AVX;
+		}
+
+		debug!("set XCR0 to 0x{:x}", xcr0);
+
+		unsafe {
+			asm!("xsetbv" :: "{ecx}"(0u32), "{eax}"(xcr0 as u32), "{edx}"((xcr0 >> 32) as u32) :: "volatile");
+			XSAVE_MASK = xcr0;
+			SUPPORTS_XSAVE = true;
+		}
+	}
+
+	// initialize the FPU of the boot processor
+	unsafe {
+		asm!("fninit" :::: "volatile");
+	}
+
 	let has_syscall = match cpuid.get_extended_function_info() {
 		Some(finfo) => finfo.has_syscall_sysret(),
 		None => false
@@ -164,7 +476,7 @@ pub fn init() {
 		wrmsr(IA32_EFER, rdmsr(IA32_EFER) | EFER_LMA | EFER_SCE | EFER_NXE);
 		wrmsr(IA32_STAR, (0x1Bu64 << 48) | (0x08u64 << 32));
 		wrmsr(IA32_LSTAR, syscall_handler as u64);
-		wrmsr(IA32_FMASK, 1 << 9); // clear IF flag during system call
+		wrmsr(IA32_FMASK, (1 << 9) | (1 << 10) | (1 << 18)); // clear IF, DF and AC flag during system call
 
 		// reset GS registers
 		wrmsr(IA32_KERNEL_GS_BASE, 0);
@@ -182,6 +494,12 @@ pub fn init() {
 	if supports_1gib_pages() {
 		info!("System supports 1GiB pages");
 	}
+	if supports_xsave() {
+		info!("System supports xsave / xrstor");
+	}
+	if supports_rdrand() {
+		info!("System supports rdrand");
+	}
 	debug!("Physical address bits {}", get_physical_address_bits());
 	debug!("Linear address bits {}", get_linear_address_bits());
 	debug!("CR0: {:?}", cr0);
@@ -0,0 +1,146 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Real-time clock of the CMOS, which is only read once at boot time
+
+use logging::*;
+use x86::io::*;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const RTC_SECONDS: u8 = 0x00;
+const RTC_MINUTES: u8 = 0x02;
+const RTC_HOURS: u8 = 0x04;
+const RTC_DAY_OF_MONTH: u8 = 0x07;
+const RTC_MONTH: u8 = 0x08;
+const RTC_YEAR: u8 = 0x09;
+const RTC_STATUS_A: u8 = 0x0A;
+const RTC_STATUS_B: u8 = 0x0B;
+
+/// The RTC updates its registers
+const RTC_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+/// The hours are in the 24 hour format
+const RTC_24_HOUR_FORMAT: u8 = 1 << 1;
+/// The registers contain binary values instead of BCD values
+const RTC_BINARY_FORMAT: u8 = 1 << 2;
+/// Flag of the hours in the 12 hour format
+const RTC_PM: u8 = 1 << 7;
+
+/// Maximum number of polls of the update flag. Without a CMOS (e.g. in
+/// ehyve), every register reads as 0xFF and the flag is always set.
+const RTC_UPDATE_RETRIES: usize = 100000;
+
+/// Maximum number of attempts to read the same date twice in a row
+const RTC_READ_RETRIES: usize = 10;
+
+/// Seconds since the epoch at boot time
+static mut BOOT_TIME: u64 = 0;
+
+unsafe fn read_register(register: u8) -> u8 {
+	// bit 7 keeps the NMI disabled during the access
+	outb(CMOS_ADDRESS, register | 0x80);
+	inb(CMOS_DATA)
+}
+
+/// Read the date registers after the RTC has finished a pending update
+unsafe fn read_date() -> Option<[u8; 6]> {
+	let mut retries = 0;
+	while read_register(RTC_STATUS_A) & RTC_UPDATE_IN_PROGRESS != 0 {
+		retries += 1;
+		if retries >= RTC_UPDATE_RETRIES {
+			return None;
+		}
+	}
+
+	Some([
+		read_register(RTC_SECONDS),
+		read_register(RTC_MINUTES),
+		read_register(RTC_HOURS),
+		read_register(RTC_DAY_OF_MONTH),
+		read_register(RTC_MONTH),
+		read_register(RTC_YEAR)
+	])
+}
+
+/// Read the date until two consecutive reads are identical
+unsafe fn read_stable_date() -> Option<[u8; 6]> {
+	let mut date = read_date()?;
+
+	for _ in 0..RTC_READ_RETRIES {
+		let next = read_date()?;
+		if next == date {
+			return Some(date);
+		}
+		date = next;
+	}
+
+	None
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+	(value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Returns the number of days between 1970-01-01 and the given date
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+	// the year starts in March => the leap day is the last day of the year
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year / 400;
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146097 + day_of_era - 719468
+}
+
+/// Returns the wall-clock time at boot time in seconds since the epoch
+pub fn get_boot_time() -> u64 {
+	unsafe { BOOT_TIME }
+}
+
+/// Read the wall-clock time from the RTC and return the seconds since the epoch
+fn read_time() -> Option<u64> {
+	let (date, status) = unsafe { (read_stable_date()?, read_register(RTC_STATUS_B)) };
+
+	let pm = date[2] & RTC_PM != 0;
+	let mut date = date;
+	date[2] &= !RTC_PM;
+	if status & RTC_BINARY_FORMAT == 0 {
+		for value in date.iter_mut() {
+			*value = bcd_to_binary(*value);
+		}
+	}
+
+	let (second, minute, hour, day, month, year) = (date[0], date[1], date[2], date[3], date[4], date[5]);
+	let hour = if status & RTC_24_HOUR_FORMAT == 0 {
+		// 12 AM is midnight and 12 PM is noon
+		(hour % 12) + if pm { 12 } else { 0 }
+	} else {
+		hour
+	};
+
+	if second >= 60 || minute >= 60 || hour >= 24 || day < 1 || day > 31
+		|| month < 1 || month > 12 || year >= 100 {
+		return None;
+	}
+
+	// the century register isn't standardized
+	let year = if year < 70 { 2000 + year as u64 } else { 1900 + year as u64 };
+
+	info!("Boot time {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, hour, minute, second);
+
+	Some(days_since_epoch(year, month as u64, day as u64) * 86400
+		+ hour as u64 * 3600 + minute as u64 * 60 + second as u64)
+}
+
+pub fn init() {
+	match read_time() {
+		Some(time) => unsafe { BOOT_TIME = time; },
+		None => error!("Unable to read the RTC, the real time starts at the epoch")
+	}
+}
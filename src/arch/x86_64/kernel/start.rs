@@ -11,6 +11,147 @@ extern {
 	pub fn main();
 }
 
+// Multiboot headers and the 32-bit trampoline for boot loaders like
+// GRUB or QEMU (`-kernel`). ehyve ignores both headers and starts the
+// kernel directly in 64-bit mode at `_start`.
+//
+// A Multiboot compliant boot loader enters `_mboot_start` in 32-bit protected
+// mode without paging. The trampoline stores the Multiboot magic number and the
+// address of the boot information, identity-maps the first 4 GiB with 2 MiB
+// pages, switches to long mode and finally continues at `_start`.
+#[cfg(not(test))]
+global_asm!(r#"
+.section .mboot, "a"
+
+/* Multiboot2 header, must be 8-byte aligned within the first 32 KiB */
+.align 8
+mb2_header_start:
+	.long 0xe85250d6
+	.long 0
+	.long mb2_header_end - mb2_header_start
+	.long 0x100000000 - (0xe85250d6 + (mb2_header_end - mb2_header_start))
+	/* entry address tag, because the ELF entry point is the 64-bit `_start` */
+	.align 8
+	.short 3
+	.short 0
+	.long 12
+	.long _mboot_start
+	/* end tag */
+	.align 8
+	.short 0
+	.short 0
+	.long 8
+mb2_header_end:
+
+/* Multiboot header, required by QEMU's `-kernel` option, which supports only
+ * the first version of the specification. QEMU doesn't load 64-bit ELF files
+ * => use the address fields to describe the kernel image. */
+.align 4
+mb1_header:
+	.long 0x1BADB002
+	.long 0x00010003
+	.long 0x100000000 - (0x1BADB002 + 0x00010003)
+	.long mb1_header
+	.long kernel_start
+	.long __bss_start
+	.long kernel_end
+	.long _mboot_start
+
+.section .text
+.code32
+.global _mboot_start
+_mboot_start:
+	cli
+	movl %eax, MBOOT_MAGIC
+	movl %ebx, MBOOT_INFO
+	movl $boot_stack_top, %esp
+
+	/* PML4[0] points to the PDPT */
+	movl $boot_pdpt, %eax
+	orl $0x3, %eax
+	movl %eax, boot_pml4
+	movl $0, boot_pml4 + 4
+
+	/* PDPT[0..3] point to the four page directories */
+	movl $boot_pd, %eax
+	orl $0x3, %eax
+	xorl %ecx, %ecx
+1:
+	movl %eax, boot_pdpt(,%ecx,8)
+	movl $0, boot_pdpt + 4(,%ecx,8)
+	addl $0x1000, %eax
+	incl %ecx
+	cmpl $4, %ecx
+	jne 1b
+
+	/* identity-map 4 GiB with present, writable 2 MiB pages */
+	xorl %ecx, %ecx
+2:
+	movl %ecx, %eax
+	shll $21, %eax
+	orl $0x83, %eax
+	movl %eax, boot_pd(,%ecx,8)
+	movl $0, boot_pd + 4(,%ecx,8)
+	incl %ecx
+	cmpl $2048, %ecx
+	jne 2b
+
+	movl $boot_pml4, %eax
+	movl %eax, %cr3
+
+	/* enable PAE */
+	movl %cr4, %eax
+	orl $0x20, %eax
+	movl %eax, %cr4
+
+	/* enable long mode (EFER.LME) */
+	movl $0xC0000080, %ecx
+	rdmsr
+	orl $0x100, %eax
+	wrmsr
+
+	/* enable paging and protected mode */
+	movl %cr0, %eax
+	orl $0x80000001, %eax
+	movl %eax, %cr0
+
+	lgdt boot_gdt_ptr
+	ljmp $0x08, $mboot_start64
+
+.code64
+mboot_start64:
+	movw $0x10, %ax
+	movw %ax, %ds
+	movw %ax, %es
+	movw %ax, %ss
+	xorw %ax, %ax
+	movw %ax, %fs
+	movw %ax, %gs
+	jmp _start
+
+.section .rodata
+.align 8
+boot_gdt:
+	.quad 0x0000000000000000
+	.quad 0x00AF9A000000FFFF
+	.quad 0x00CF92000000FFFF
+boot_gdt_ptr:
+	.short boot_gdt_ptr - boot_gdt - 1
+	.long boot_gdt
+
+.section .bss
+.align 4096
+boot_pml4:
+	.skip 4096
+boot_pdpt:
+	.skip 4096
+boot_pd:
+	.skip 4*4096
+boot_stack:
+	.skip 4096
+boot_stack_top:
+"#);
+
 #[cfg(not(test))]
 #[no_mangle]
 #[naked]
@@ -35,6 +35,11 @@ pub extern "C" fn switch(_old_stack: *mut usize, _new_stack: usize) {
 			rdgsbase %rdx\n\t\
 			push %rax\n\t\
 			push %rdx\n\t\
+			// store the inactive gs base (user or kernel)\n\t\
+			swapgs\n\t\
+			rdgsbase %rax\n\t\
+			swapgs\n\t\
+			push %rax\n\t\
 			mov %rsp, (%rdi)\n\t\
 			mov %rsi, %rsp\n\t\
 			// Set task switched flag \n\t\
@@ -45,6 +50,10 @@ pub extern "C" fn switch(_old_stack: *mut usize, _new_stack: usize) {
 			call set_current_kernel_stack \n\t\
 			// restore context \n\t\
 			pop %r15\n\t\
+			swapgs\n\t\
+			wrgsbase %r15\n\t\
+			swapgs\n\t\
+			pop %r15\n\t\
 			wrgsbase %r15\n\t\
 			pop %r15\n\t\
 			wrfsbase %r15\n\t\
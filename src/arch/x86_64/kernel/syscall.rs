@@ -5,12 +5,24 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use syscall::NO_SYSCALLS;
+
+/// Number of registers, which the system call handler stores on top
+/// of the kernel stack (including the user-level stack pointer)
+pub const SYSCALL_FRAME_REGISTERS: usize = 11;
+
 #[no_mangle]
 #[naked]
 pub unsafe extern "C" fn syscall_handler() {
 	asm!(
-		// save context, see x86_64 ABI
-		"push %rcx\n\t\
+		// switch to kernel stack, the user-level stack pointer
+		// is stored on top of the kernel stack
+		"swapgs\n\t\
+		mov %rsp, %gs:-8\n\t\
+		rdgsbase %rsp\n\t\
+		sub $$8, %rsp\n\t\
+		// save context, see x86_64 ABI\n\t\
+		push %rcx\n\t\
 		push %rdx\n\t\
 		push %rsi\n\t\
 		push %rdi\n\t\
@@ -26,25 +38,50 @@ pub unsafe extern "C" fn syscall_handler() {
 		mov $$0x10, %rcx\n\t\
 		mov %rcx, %ds\n\t\
 		mov %rcx, %es\n\t\
-		// switch to kernel stack\n\t
-		swapgs\n\t
-		mov %rsp, %rcx\n\t
-		rdgsbase %rsp\n\t
-		push %rcx
+		// align the stack to 16 bytes\n\t\
+		sub $$8, %rsp\n\t\
 		// copy 4th argument to rcx to adhere x86_64 ABI \n\t\
 		mov %r10, %rcx\n\t\
 		sti\n\t\
-		call *SYSHANDLER_TABLE(,%rax,8)\n\t
-		// restore context, see x86_64 ABI \n\t\
+		// numbers behind the table (NO_SYSCALLS) are invalid\n\t\
+		cmp $0, %rax\n\t\
+		jb 1f\n\t\
+		call sys_invalid\n\t\
+		jmp 2f\n\t\
+		1:\n\t\
+		call *SYSHANDLER_TABLE(,%rax,8)\n\t\
+		2:\n\t\
 		cli\n\t\
-		// switch to user stack\n\t
-		pop %rcx\n\t
-		mov %rcx, %rsp\n\t
+		add $$8, %rsp\n\t\
+		// restore context, see x86_64 ABI \n\t\
+		pop %rcx\n\t\
+		mov %rcx, %es\n\t\
+		pop %rcx\n\t\
+		mov %rcx, %ds\n\t\
+		pop %r11\n\t\
+		pop %r10\n\t\
+		pop %r9\n\t\
+		pop %r8\n\t\
+		pop %rdi\n\t\
+		pop %rsi\n\t\
+		pop %rdx\n\t\
+		pop %rcx\n\t\
+		// switch to user stack\n\t\
+		mov (%rsp), %rsp\n\t\
 		swapgs\n\t\
-		// restore context
+		sysretq" :: "i"(NO_SYSCALLS) :: "volatile");
+}
+
+/// Entry point of a forked task, which leaves the system call
+/// of the parent with the return value in rax
+#[naked]
+pub unsafe extern "C" fn syscall_fork_return() {
+	asm!(
+		"cli\n\t\
+		// restore context\n\t\
 		pop %rcx\n\t\
 		mov %rcx, %es\n\t\
-	    pop %rcx\n\t\
+		pop %rcx\n\t\
 		mov %rcx, %ds\n\t\
 		pop %r11\n\t\
 		pop %r10\n\t\
@@ -54,5 +91,8 @@ pub unsafe extern "C" fn syscall_handler() {
 		pop %rsi\n\t\
 		pop %rdx\n\t\
 		pop %rcx\n\t\
+		// switch to user stack\n\t\
+		mov (%rsp), %rsp\n\t\
+		swapgs\n\t\
 		sysretq" :::: "volatile");
 }
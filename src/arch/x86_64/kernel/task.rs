@@ -7,16 +7,21 @@
 
 //! Architecture dependent interface to initialize a task
 
+use core::ptr;
 use core::mem::size_of;
 use compiler_builtins::mem::memset;
 use scheduler::task::*;
 use scheduler::{do_exit, get_current_taskid};
 use arch::processor::halt;
+use arch::x86_64::kernel::syscall::{syscall_fork_return,SYSCALL_FRAME_REGISTERS};
+use x86::msr::{rdmsr, IA32_FS_BASE, IA32_KERNEL_GS_BASE};
 use consts::*;
 use logging::*;
 
 #[repr(C, packed)]
 struct State {
+	/// inactive GS register, which is activated by swapgs
+	kernel_gs: u64,
 	/// GS register
 	gs: u64,
 	/// FS register
@@ -75,7 +80,7 @@ impl TaskFrame for Task {
 		unsafe {
 			let mut stack: *mut u64 = ((*self.stack).top()) as *mut u64;
 
-			memset((*self.stack).bottom() as *mut u8, 0xCD, STACK_SIZE);
+			memset((*self.stack).bottom() as *mut u8, STACK_PATTERN as i32, STACK_SIZE);
 
 			/* Only marker for debugging purposes, ... */
 			*stack = 0xDEADBEEFu64;
@@ -103,4 +108,36 @@ impl TaskFrame for Task {
 			self.last_stack_pointer =  stack as usize;
 		}
 	}
+
+	fn create_fork_frame(&mut self, parent: &Task)
+	{
+		unsafe {
+			let mut stack: *mut u64 = ((*self.stack).top()) as *mut u64;
+
+			memset((*self.stack).bottom() as *mut u8, STACK_PATTERN as i32, STACK_SIZE);
+
+			/* The syscall handler stores the user-level stack pointer and
+			 * the user-level registers on top of the kernel stack. */
+			stack = (stack as usize - SYSCALL_FRAME_REGISTERS * size_of::<u64>()) as *mut u64;
+			ptr::copy_nonoverlapping(((*parent.stack).top() - SYSCALL_FRAME_REGISTERS * size_of::<u64>()) as *const u64,
+				stack, SYSCALL_FRAME_REGISTERS);
+			stack = (stack as usize - size_of::<State>()) as *mut u64;
+
+			let state: *mut State = stack as *mut State;
+			memset(state as *mut u8, 0x00, size_of::<State>());
+
+			(*state).rsp = (stack as usize + size_of::<State>()) as u64;
+			(*state).gs = ((*self.stack).top()) as u64;
+			// the parent is in a system call => its user-level gs base is inactive
+			(*state).kernel_gs = rdmsr(IA32_KERNEL_GS_BASE);
+			(*state).fs = rdmsr(IA32_FS_BASE);
+
+			/* the child leaves the system call with the return value 0 */
+			(*state).rax = 0;
+			(*state).rip = (syscall_fork_return as *const()) as u64;
+			(*state).rflags = 0x1202u64;
+
+			self.last_stack_pointer = stack as usize;
+		}
+	}
 }
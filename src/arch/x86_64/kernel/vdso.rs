@@ -0,0 +1,344 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Virtual dynamic shared object (vDSO)
+//!
+//! The vDSO is a tiny shared library, which the kernel maps into every user
+//! space. Its functions read the time from a shared data page and the time
+//! stamp counter without a system call. The data page is located directly
+//! in front of the image and is updated by the timer interrupt.
+//!
+//! The image is assembled by hand and contains only the parts of an ELF
+//! file, which the dynamic linkers of glibc and musl require: a single
+//! PT_LOAD segment, the dynamic section, a hash table, the symbol table
+//! and the string table. Without version information, the symbols match
+//! every requested version (e.g. LINUX_2.6).
+
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{compiler_fence, Ordering};
+use arch::x86_64::kernel::pit;
+use arch::x86_64::kernel::rtc;
+use arch::x86_64::mm::paging::{self, BasePageSize, PageSize, PageTableEntryFlags};
+use mm::vma::VmaFlags;
+use time;
+use logging::*;
+
+extern "C" {
+	static vdso_start: u8;
+	static vdso_end: u8;
+}
+
+/// Data page of the vDSO. The layout has to match the offsets of the image.
+#[repr(C, align(4096))]
+struct VdsoData {
+	/// Sequence counter, which is odd during an update
+	seq: u32,
+	/// Number of the current processor
+	cpu: u32,
+	/// Frequency of the time stamp counter in Hz, zero if it is unknown
+	tsc_frequency: u64,
+	/// Value of the time stamp counter at the end of the TSC calibration,
+	/// which is the origin of the monotonic clock
+	tsc_start: u64,
+	/// Wall-clock time at boot time in nanoseconds since the epoch
+	boot_ns: u64,
+	/// Nanoseconds since boot time at the last timer interrupt
+	coarse_ns: u64
+}
+
+static mut VDSO_DATA: VdsoData = VdsoData {
+	seq: 0,
+	cpu: 0,
+	tsc_frequency: 0,
+	tsc_start: 0,
+	boot_ns: 0,
+	coarse_ns: 0
+};
+
+#[cfg(not(test))]
+global_asm!(r#"
+.section .rodata.vdso, "a"
+.p2align 12
+vdso_start:
+
+/* the data page is located in front of the image */
+.set vdso_data, vdso_start - 4096
+.set vdso_seq, vdso_data
+.set vdso_cpu, vdso_data + 4
+.set vdso_tsc_frequency, vdso_data + 8
+.set vdso_tsc_start, vdso_data + 16
+.set vdso_boot_ns, vdso_data + 24
+.set vdso_coarse_ns, vdso_data + 32
+
+/* ELF header */
+	.byte 0x7f, 0x45, 0x4c, 0x46, 2, 1, 1, 0     /* ELF magic, 64 bit, little endian */
+	.quad 0
+	.short 3                        /* ET_DYN */
+	.short 62                       /* EM_X86_64 */
+	.long 1
+	.quad 0                         /* no entry point */
+	.quad vdso_phdr - vdso_start
+	.quad 0                         /* no section headers */
+	.long 0
+	.short 64
+	.short 56
+	.short 2
+	.short 64
+	.short 0
+	.short 0
+
+/* program headers */
+vdso_phdr:
+	.long 1                         /* PT_LOAD */
+	.long 5                         /* PF_R | PF_X */
+	.quad 0
+	.quad 0
+	.quad 0
+	.quad vdso_end - vdso_start
+	.quad vdso_end - vdso_start
+	.quad 4096
+	.long 2                         /* PT_DYNAMIC */
+	.long 4                         /* PF_R */
+	.quad vdso_dynamic - vdso_start
+	.quad vdso_dynamic - vdso_start
+	.quad vdso_dynamic - vdso_start
+	.quad vdso_dynamic_end - vdso_dynamic
+	.quad vdso_dynamic_end - vdso_dynamic
+	.quad 8
+
+/* dynamic section */
+.p2align 3
+vdso_dynamic:
+	.quad 4, vdso_hash - vdso_start                 /* DT_HASH */
+	.quad 5, vdso_dynstr - vdso_start               /* DT_STRTAB */
+	.quad 6, vdso_dynsym - vdso_start               /* DT_SYMTAB */
+	.quad 10, vdso_dynstr_end - vdso_dynstr         /* DT_STRSZ */
+	.quad 11, 24                                    /* DT_SYMENT */
+	.quad 14, vdso_name_soname - vdso_dynstr        /* DT_SONAME */
+	.quad 0, 0                                      /* DT_NULL */
+vdso_dynamic_end:
+
+/* hash table with a single bucket, which chains all symbols */
+vdso_hash:
+	.long 1, 5
+	.long 1
+	.long 0, 2, 3, 4, 0
+
+/* symbol table, the symbols are global functions */
+vdso_dynsym:
+	.long 0
+	.byte 0, 0
+	.short 0
+	.quad 0, 0
+	.long vdso_name_clock_gettime - vdso_dynstr
+	.byte 0x12, 0
+	.short 1
+	.quad __vdso_clock_gettime - vdso_start, 0
+	.long vdso_name_gettimeofday - vdso_dynstr
+	.byte 0x12, 0
+	.short 1
+	.quad __vdso_gettimeofday - vdso_start, 0
+	.long vdso_name_time - vdso_dynstr
+	.byte 0x12, 0
+	.short 1
+	.quad __vdso_time - vdso_start, 0
+	.long vdso_name_getcpu - vdso_dynstr
+	.byte 0x12, 0
+	.short 1
+	.quad __vdso_getcpu - vdso_start, 0
+
+/* string table */
+vdso_dynstr:
+	.byte 0
+vdso_name_soname:
+	.asciz "linux-vdso.so.1"
+vdso_name_clock_gettime:
+	.asciz "__vdso_clock_gettime"
+vdso_name_gettimeofday:
+	.asciz "__vdso_gettimeofday"
+vdso_name_time:
+	.asciz "__vdso_time"
+vdso_name_getcpu:
+	.asciz "__vdso_getcpu"
+vdso_dynstr_end:
+
+.p2align 4
+/* Returns the nanoseconds since boot time in rax. Clobbers rcx and rdx. */
+vdso_monotonic_ns:
+	rdtsc
+	shlq $32, %rdx
+	orq %rdx, %rax
+	subq vdso_tsc_start(%rip), %rax
+	movq $1000000000, %rcx
+	mulq %rcx
+	divq vdso_tsc_frequency(%rip)
+	ret
+
+/* Returns the nanoseconds since boot time at the last timer interrupt in rax.
+ * Clobbers r8. */
+vdso_read_coarse_ns:
+	movl vdso_seq(%rip), %r8d
+	testl $1, %r8d
+	jnz 1f
+	movq vdso_coarse_ns(%rip), %rax
+	cmpl vdso_seq(%rip), %r8d
+	jne vdso_read_coarse_ns
+	ret
+1:	pause
+	jmp vdso_read_coarse_ns
+
+/* int clock_gettime(clockid_t clock, struct timespec *tp) */
+__vdso_clock_gettime:
+	cmpl $5, %edi                   /* CLOCK_REALTIME_COARSE */
+	je 3f
+	cmpl $6, %edi                   /* CLOCK_MONOTONIC_COARSE */
+	je 4f
+	cmpq $0, vdso_tsc_frequency(%rip)
+	je 5f
+	cmpl $0, %edi                   /* CLOCK_REALTIME */
+	je 1f
+	cmpl $1, %edi                   /* CLOCK_MONOTONIC */
+	je 2f
+	cmpl $4, %edi                   /* CLOCK_MONOTONIC_RAW */
+	je 2f
+	cmpl $7, %edi                   /* CLOCK_BOOTTIME */
+	je 2f
+	jmp 5f
+1:	call vdso_monotonic_ns
+	addq vdso_boot_ns(%rip), %rax
+	jmp 6f
+2:	call vdso_monotonic_ns
+	jmp 6f
+3:	call vdso_read_coarse_ns
+	addq vdso_boot_ns(%rip), %rax
+	jmp 6f
+4:	call vdso_read_coarse_ns
+	jmp 6f
+	/* the kernel handles all other clocks */
+5:	movl $228, %eax
+	syscall
+	ret
+	/* convert the nanoseconds to a timespec */
+6:	xorl %edx, %edx
+	movq $1000000000, %rcx
+	divq %rcx
+	movq %rax, (%rsi)
+	movq %rdx, 8(%rsi)
+	xorl %eax, %eax
+	ret
+
+/* int gettimeofday(struct timeval *tv, struct timezone *tz) */
+__vdso_gettimeofday:
+	cmpq $0, vdso_tsc_frequency(%rip)
+	je 3f
+	testq %rdi, %rdi
+	jz 1f
+	call vdso_monotonic_ns
+	addq vdso_boot_ns(%rip), %rax
+	xorl %edx, %edx
+	movq $1000000000, %rcx
+	divq %rcx
+	movq %rax, (%rdi)
+	movq %rdx, %rax
+	xorl %edx, %edx
+	movq $1000, %rcx
+	divq %rcx
+	movq %rax, 8(%rdi)
+	/* the clock is always UTC */
+1:	testq %rsi, %rsi
+	jz 2f
+	movq $0, (%rsi)
+2:	xorl %eax, %eax
+	ret
+3:	movl $96, %eax
+	syscall
+	ret
+
+/* time_t time(time_t *tloc) */
+__vdso_time:
+	call vdso_read_coarse_ns
+	addq vdso_boot_ns(%rip), %rax
+	xorl %edx, %edx
+	movq $1000000000, %rcx
+	divq %rcx
+	testq %rdi, %rdi
+	jz 1f
+	movq %rax, (%rdi)
+1:	ret
+
+/* int getcpu(unsigned *cpu, unsigned *node, void *cache) */
+__vdso_getcpu:
+	testq %rdi, %rdi
+	jz 1f
+	movl vdso_cpu(%rip), %eax
+	movl %eax, (%rdi)
+1:	testq %rsi, %rsi
+	jz 2f
+	movl $0, (%rsi)
+2:	xorl %eax, %eax
+	ret
+
+.p2align 12
+vdso_end:
+"#);
+
+fn image_address() -> usize {
+	unsafe { &vdso_start as *const u8 as usize }
+}
+
+fn image_size() -> usize {
+	unsafe { &vdso_end as *const u8 as usize - &vdso_start as *const u8 as usize }
+}
+
+/// Returns the size of the vDSO in the user space, which includes the data page
+pub fn get_vdso_size() -> usize {
+	BasePageSize::SIZE + image_size()
+}
+
+/// Map the data page at `address` and the image of the vDSO behind it into
+/// the current address space. The pages belong to the kernel and, consequently,
+/// they are never released. Returns the address of the image.
+pub fn map_vdso(address: usize) -> usize {
+	let data = unsafe { &VDSO_DATA as *const VdsoData as usize };
+
+	paging::map::<BasePageSize>(address, paging::virtual_to_physical(data), 1,
+		VmaFlags::READ.page_flags() | PageTableEntryFlags::UNMANAGED);
+
+	let image = address + BasePageSize::SIZE;
+	let flags = (VmaFlags::READ | VmaFlags::EXECUTE).page_flags() | PageTableEntryFlags::UNMANAGED;
+	for offset in (0..image_size()).step_by(BasePageSize::SIZE) {
+		paging::map::<BasePageSize>(image + offset, paging::virtual_to_physical(image_address() + offset), 1, flags);
+	}
+
+	debug!("Map vDSO at 0x{:x}", image);
+
+	image
+}
+
+/// Update the coarse time of the data page, must be called by the timer interrupt
+pub fn update() {
+	unsafe {
+		let seq = read_volatile(&VDSO_DATA.seq);
+
+		// an odd sequence number signals the update to the readers
+		write_volatile(&mut VDSO_DATA.seq, seq.wrapping_add(1));
+		compiler_fence(Ordering::SeqCst);
+		write_volatile(&mut VDSO_DATA.coarse_ns, time::monotonic_ns());
+		compiler_fence(Ordering::SeqCst);
+		write_volatile(&mut VDSO_DATA.seq, seq.wrapping_add(2));
+	}
+}
+
+pub fn init() {
+	unsafe {
+		VDSO_DATA.tsc_frequency = pit::get_tsc_frequency();
+		VDSO_DATA.tsc_start = pit::get_tsc_start();
+		VDSO_DATA.boot_ns = rtc::get_boot_time() * time::NSEC_PER_SEC;
+	}
+
+	info!("vDSO at 0x{:x} ({} bytes)", image_address(), image_size());
+}
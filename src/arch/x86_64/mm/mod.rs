@@ -8,6 +8,7 @@
 pub mod paging;
 pub mod physicalmem;
 pub mod virtualmem;
+pub mod uaccess;
 
 use arch::x86_64::kernel::get_memfile;
 use self::paging::{PageSize,BasePageSize,PageTableEntryFlags};
@@ -17,6 +18,10 @@ pub fn init() {
 	physicalmem::init();
 	virtualmem::init();
 
+	// the frame table is identity-mapped behind the kernel
+	let (table_start, table_size) = physicalmem::get_frame_table();
+	virtualmem::reserve(table_start, table_size);
+
 	let (start, len) = get_memfile();
 	if len > 0 {
 		// Map file into the kernel space
@@ -7,11 +7,14 @@
 
 #![allow(dead_code)]
 
-use compiler_builtins::mem::memset;
+use alloc::vec::Vec;
+use compiler_builtins::mem::{memcpy,memset};
 use arch::x86_64::mm::physicalmem;
 use arch::x86_64::mm::virtualmem;
+use arch::x86_64::mm::uaccess;
 use arch::x86_64::kernel::processor;
 use arch::x86_64::kernel::irq;
+use core::{cmp,slice};
 use core::mem::size_of;
 use core::marker::PhantomData;
 use num_traits::CheckedShr;
@@ -21,6 +24,7 @@ use x86::controlregs;
 use x86::irq::*;
 use logging::*;
 use consts::*;
+use mm::vma::VmaFlags;
 
 /// Pointer to the root page table (PML4)
 const PML4_ADDRESS: *mut PageTable<PML4> = 0xFFFF_FFFF_FFFF_F000 as *mut PageTable<PML4>;
@@ -69,6 +73,14 @@ bitflags! {
 		/// be flushed from the TLB when CR3 is reset.
 		const GLOBAL = 1 << 8;
 
+		/// Available for software: Set if the page is shared by several address spaces and
+		/// has to be copied on the first write access.
+		const COPY_ON_WRITE = 1 << 9;
+
+		/// Available for software: Set if the page frame isn't managed by the physical
+		/// memory allocator (e.g. a page of a ROM file) and must not be released.
+		const UNMANAGED = 1 << 10;
+
 		/// Set if code execution shall be disabled for memory referenced by this entry.
 		const EXECUTE_DISABLE = 1 << 63;
     }
@@ -131,6 +143,11 @@ impl PageTableEntry {
 		(self.physical_address_and_flags & PageTableEntryFlags::USER_ACCESSIBLE.bits()) != 0
 	}
 
+	/// Return the flags of this entry.
+	pub fn flags(&self) -> PageTableEntryFlags {
+		PageTableEntryFlags::from_bits_truncate(self.physical_address_and_flags & !self.address())
+	}
+
 	/// Mark this as a valid (present) entry and set address translation and flags.
 	///
 	/// # Arguments
@@ -355,7 +372,9 @@ trait PageTableMethods {
 	fn get_page_table_entry<S: PageSize>(&self, page: Page<S>) -> Option<PageTableEntry>;
 	fn map_page_in_this_table<S: PageSize>(&mut self, page: Page<S>, physical_address: usize, flags: PageTableEntryFlags) -> bool;
 	fn map_page<S: PageSize>(&mut self, page: Page<S>, physical_address: usize, flags: PageTableEntryFlags) -> bool;
+	fn unmap_page<S: PageSize>(&mut self, page: Page<S>);
 	fn drop_user_space(&mut self);
+	fn share_user_space(&mut self, base: usize, pages: &mut Vec<(usize, PageTableEntry)>);
 }
 
 impl<L: PageTableLevel> PageTableMethods for PageTable<L> {
@@ -399,16 +418,48 @@ impl<L: PageTableLevel> PageTableMethods for PageTable<L> {
 		for index in 0..last {
 			if self.entries[index].is_present() && self.entries[index].is_user() {
 				let address = self.entries[index].address();
+				let unmanaged = self.entries[index].flags().contains(PageTableEntryFlags::UNMANAGED);
 
-				if !(address >= mm::kernel_start_address() &&
+				if !unmanaged && !(address >= mm::kernel_start_address() &&
 					 address < mm::kernel_end_address()) {
 						debug!("Free page frame at 0x{:x}", address);
-						physicalmem::deallocate(address, BasePageSize::SIZE);
+						physicalmem::release(address);
 				}
 			}
 		}
 	}
 
+	/// Shares all user pages of this table with another address space and
+	/// appends them to `pages`. Writable pages are marked as copy-on-write.
+	///
+	/// This is the default implementation called only for PT.
+	/// It is overridden by a specialized implementation for all tables with sub tables (all except PT).
+	default fn share_user_space(&mut self, base: usize, pages: &mut Vec<(usize, PageTableEntry)>) {
+		let last = 1 << PAGE_MAP_BITS;
+
+		for index in 0..last {
+			if self.entries[index].is_present() && self.entries[index].is_user() {
+				let address = self.entries[index].address();
+
+				// only frames of the physical memory allocator have a reference counter
+				if address >= mm::kernel_end_address()
+					&& !self.entries[index].flags().contains(PageTableEntryFlags::UNMANAGED) {
+					let mut flags = self.entries[index].flags();
+
+					if flags.contains(PageTableEntryFlags::WRITABLE) {
+						flags.remove(PageTableEntryFlags::WRITABLE);
+						flags.insert(PageTableEntryFlags::COPY_ON_WRITE);
+						self.entries[index].set(address, flags);
+					}
+
+					physicalmem::share(address);
+				}
+
+				pages.push((base | (index << PAGE_BITS), self.entries[index]));
+			}
+		}
+	}
+
 	/// Maps a single page to the given physical address.
 	/// Returns whether an existing entry was updated. You can use this return value to flush TLBs.
 	///
@@ -417,6 +468,18 @@ impl<L: PageTableLevel> PageTableMethods for PageTable<L> {
 	default fn map_page<S: PageSize>(&mut self, page: Page<S>, physical_address: usize, flags: PageTableEntryFlags) -> bool {
 		self.map_page_in_this_table::<S>(page, physical_address, flags)
 	}
+
+	/// Removes the mapping of a single page.
+	///
+	/// This is the default implementation called only for PT.
+	/// It is overridden by a specialized implementation for all tables with sub tables (all except PT).
+	default fn unmap_page<S: PageSize>(&mut self, page: Page<S>) {
+		assert!(L::LEVEL == S::MAP_LEVEL);
+		let index = page.table_index::<L>();
+
+		self.entries[index].physical_address_and_flags = 0;
+		page.flush_from_tlb();
+	}
 }
 
 impl<L: PageTableLevelWithSubtables> PageTableMethods for PageTable<L> where L::SubtableLevel: PageTableLevel {
@@ -466,6 +529,29 @@ impl<L: PageTableLevelWithSubtables> PageTableMethods for PageTable<L> where L::
 
 	}
 
+	/// Shares all user pages of this table with another address space and
+	/// appends them to `pages`.
+	///
+	/// This is the implementation for all tables with subtables (PML4, PDPT, PDT).
+	/// It overrides the default implementation above.
+	fn share_user_space(&mut self, base: usize, pages: &mut Vec<(usize, PageTableEntry)>) {
+		let last = 1 << PAGE_MAP_BITS;
+		let table_address = self as *const PageTable<L> as usize;
+
+		for index in 0..last {
+			if self.entries[index].is_present() && self.entries[index].is_user() {
+				// currently, the user space uses only 4KB pages
+				assert!(!self.entries[index].is_huge(), "Huge pages in the user space aren't supported");
+
+				// Calculate the address of the subtable.
+				let subtable_address = (table_address << PAGE_MAP_BITS) | (index << PAGE_BITS);
+				let subtable = unsafe { &mut *(subtable_address as *mut PageTable<L::SubtableLevel>) };
+
+				subtable.share_user_space(base | (index << PAGE_BITS << L::LEVEL * PAGE_MAP_BITS), pages);
+			}
+		}
+	}
+
 	/// Maps a single page to the given physical address.
 	/// Returns whether an existing entry was updated. You can use this return value to flush TLBs.
 	///
@@ -505,6 +591,25 @@ impl<L: PageTableLevelWithSubtables> PageTableMethods for PageTable<L> where L::
 			self.map_page_in_this_table::<S>(page, physical_address, flags)
 		}
 	}
+
+	/// Removes the mapping of a single page.
+	///
+	/// This is the implementation for all tables with subtables (PML4, PDPT, PDT).
+	/// It overrides the default implementation above.
+	fn unmap_page<S: PageSize>(&mut self, page: Page<S>) {
+		assert!(L::LEVEL >= S::MAP_LEVEL);
+		let index = page.table_index::<L>();
+
+		if L::LEVEL > S::MAP_LEVEL {
+			if self.entries[index].is_present() {
+				let subtable = self.subtable::<S>(page);
+				subtable.unmap_page::<S>(page);
+			}
+		} else {
+			self.entries[index].physical_address_and_flags = 0;
+			page.flush_from_tlb();
+		}
+	}
 }
 
 impl<L: PageTableLevelWithSubtables> PageTable<L> where L::SubtableLevel: PageTableLevel {
@@ -538,6 +643,13 @@ impl<L: PageTableLevelWithSubtables> PageTable<L> where L::SubtableLevel: PageTa
 		}
 	}
 
+	/// Removes the mapping of a continuous range of pages.
+	fn unmap_pages<S: PageSize>(&mut self, range: PageIter<S>) {
+		for page in range {
+			self.unmap_page(page);
+		}
+	}
+
 	fn drop_user_space(&mut self) {
 		assert!(L::LEVEL == PML4::LEVEL);
 
@@ -558,38 +670,159 @@ impl<L: PageTableLevelWithSubtables> PageTable<L> where L::SubtableLevel: PageTa
 					debug!("Free page table at 0x{:x}", address);
 					physicalmem::deallocate(address, BasePageSize::SIZE);
 				}
+
+				self.entries[index].physical_address_and_flags = 0;
 			}
 		}
 	}
 }
 
-pub extern "x86-interrupt" fn page_fault_handler(stack_frame: &mut irq::ExceptionStackFrame, error_code: u64) {
-	let mut virtual_address = unsafe { controlregs::cr2() };
+/// Resolves a write access to a copy-on-write page.
+/// Returns false, if the page isn't a copy-on-write page.
+fn resolve_copy_on_write(virtual_address: usize) -> bool {
+	let virtual_address = align_down!(virtual_address, BasePageSize::SIZE);
+	let entry = match get_page_table_entry::<BasePageSize>(virtual_address) {
+		Some(entry) => entry,
+		None => return false
+	};
+
+	let mut flags = entry.flags();
+	if !flags.contains(PageTableEntryFlags::COPY_ON_WRITE) {
+		return false;
+	}
 
-	// do we have to create the user-space stack?
-	if virtual_address > USER_SPACE_START {
-		virtual_address = align_down!(virtual_address, BasePageSize::SIZE);
+	flags.remove(PageTableEntryFlags::COPY_ON_WRITE);
+	flags.insert(PageTableEntryFlags::WRITABLE);
 
-		// Ok, user space want to have memory (for the stack / heap)
-		let physical_address = physicalmem::allocate_aligned(BasePageSize::SIZE, BasePageSize::SIZE);
+	// an unmanaged frame (e.g. of a ROM file) is always copied
+	let unmanaged = flags.contains(PageTableEntryFlags::UNMANAGED);
+	flags.remove(PageTableEntryFlags::UNMANAGED);
 
-		debug!("Map 0x{:x} into the user space at 0x{:x}", physical_address, virtual_address);
+	let physical_address = entry.address();
+	if unmanaged || physicalmem::is_shared(physical_address) {
+		let new_address = physicalmem::allocate(BasePageSize::SIZE);
 
-		map::<BasePageSize>(virtual_address, physical_address, 1,
-			PageTableEntryFlags::WRITABLE | PageTableEntryFlags::USER_ACCESSIBLE
-			| PageTableEntryFlags::EXECUTE_DISABLE);
+		debug!("Copy page frame 0x{:x} to 0x{:x} (virtual address 0x{:x})", physical_address, new_address, virtual_address);
 
+		// map the new frame temporarily into the kernel space to copy the content
+		let kernel_address = virtualmem::allocate(BasePageSize::SIZE);
+		map::<BasePageSize>(kernel_address, new_address, 1,
+			PageTableEntryFlags::WRITABLE | PageTableEntryFlags::EXECUTE_DISABLE);
 		unsafe {
-			// clear new page
-			memset(virtual_address as *mut u8, 0x00, BasePageSize::SIZE);
+			let _access = processor::UserAccess::new();
+			memcpy(kernel_address as *mut u8, virtual_address as *const u8, BasePageSize::SIZE);
+		}
+		unmap::<BasePageSize>(kernel_address, 1);
+		virtualmem::deallocate(kernel_address, BasePageSize::SIZE);
 
-			// clear cr2 to signalize that the pagefault is solved by the pagefault handler
-			controlregs::cr2_write(0);
+		map::<BasePageSize>(virtual_address, new_address, 1, flags);
+		if !unmanaged {
+			physicalmem::release(physical_address);
 		}
 	} else {
-		// Anything else is an error!
-		let pferror = PageFaultError::from_bits_truncate(error_code as u32);
+		// the frame isn't shared anymore => no copy required
+		debug!("Reuse page frame 0x{:x} at virtual address 0x{:x}", physical_address, virtual_address);
+		map::<BasePageSize>(virtual_address, physical_address, 1, flags);
+	}
+
+	true
+}
+
+/// Resolves a page fault in the user space. Returns false, if the address
+/// isn't part of a virtual memory area or the area doesn't allow the access.
+fn resolve_user_fault(virtual_address: usize, pferror: PageFaultError) -> bool {
+	// an access below the stack extends the stack
+	let vma = match scheduler::find_vma(virtual_address).or_else(|| scheduler::expand_stack(virtual_address)) {
+		Some(vma) => vma,
+		None => return false
+	};
+
+	if vma.flags.is_empty()
+		|| (pferror.contains(PageFaultError::WR) && !vma.flags.contains(VmaFlags::WRITE))
+		|| (pferror.contains(PageFaultError::ID) && !vma.flags.contains(VmaFlags::EXECUTE)) {
+		return false;
+	}
+
+	if pferror.contains(PageFaultError::P) {
+		// the page exists => only a write access to a copy-on-write page is valid
+		return pferror.contains(PageFaultError::WR) && resolve_copy_on_write(virtual_address);
+	}
 
+	let virtual_address = align_down!(virtual_address, BasePageSize::SIZE);
+	let offset = virtual_address - vma.start;
+
+	if let Some(ref file) = vma.file {
+		// read-only pages of a ROM file are mapped directly
+		let file_offset = file.offset + offset;
+		if !vma.flags.contains(VmaFlags::WRITE) && offset + BasePageSize::SIZE <= file.size
+			&& file_offset + BasePageSize::SIZE <= file.handle.len() {
+			if let Some(address) = file.handle.rom_address(file_offset) {
+				if address % BasePageSize::SIZE == 0 {
+					debug!("Map ROM page 0x{:x} into the user space at 0x{:x}", address, virtual_address);
+
+					map::<BasePageSize>(virtual_address, address, 1,
+						vma.flags.page_flags() | PageTableEntryFlags::UNMANAGED);
+					return true;
+				}
+			}
+		}
+	}
+
+	let physical_address = physicalmem::allocate_aligned(BasePageSize::SIZE, BasePageSize::SIZE);
+
+	debug!("Map 0x{:x} into the user space at 0x{:x}", physical_address, virtual_address);
+
+	// the page is writable until it is initialized
+	map::<BasePageSize>(virtual_address, physical_address, 1,
+		PageTableEntryFlags::WRITABLE | PageTableEntryFlags::USER_ACCESSIBLE
+		| PageTableEntryFlags::EXECUTE_DISABLE);
+
+	unsafe {
+		let _access = processor::UserAccess::new();
+		memset(virtual_address as *mut u8, 0x00, BasePageSize::SIZE);
+
+		// read the content of file-backed pages, the rest of the page stays zero
+		if let Some(ref file) = vma.file {
+			if offset < file.size {
+				let len = cmp::min(BasePageSize::SIZE, file.size - offset);
+				let page = slice::from_raw_parts_mut(virtual_address as *mut u8, len);
+
+				if let Err(e) = file.handle.read_at(page, file.offset + offset) {
+					error!("Unable to read page at 0x{:x} from file: {}", virtual_address, e);
+				}
+			}
+		}
+	}
+
+	map::<BasePageSize>(virtual_address, physical_address, 1, vma.flags.page_flags());
+
+	true
+}
+
+pub extern "x86-interrupt" fn page_fault_handler(stack_frame: &mut irq::ExceptionStackFrame, error_code: u64) {
+	// the interrupted code may have opened a user-access window
+	// => close it, iretq restores the AC flag
+	processor::clac();
+
+	let virtual_address = unsafe { controlregs::cr2() };
+	let pferror = PageFaultError::from_bits_truncate(error_code as u32);
+
+	let is_user_address = virtual_address >= USER_SPACE_START && virtual_address < USER_SPACE_END;
+
+	if is_user_address && resolve_user_fault(virtual_address, pferror) {
+		// clear cr2 to signalize that the pagefault is solved by the pagefault handler
+		unsafe { controlregs::cr2_write(0); }
+	} else if uaccess::fixup_user_access(stack_frame) {
+		// the kernel accessed an invalid user-space address => abort the copy
+		debug!("Invalid user-space access at {:#X}", virtual_address);
+
+		unsafe { controlregs::cr2_write(0); }
+	} else {
+		// Anything else is an error!
+		if pferror.contains(PageFaultError::US) {
+			// signals aren't supported => a segmentation fault terminates the task
+			error!("Segmentation fault of task {} at {:#X}", scheduler::get_current_taskid(), virtual_address);
+		}
 		error!("Page Fault (#PF) Exception: {:#?}", stack_frame);
 		error!("virtual_address = {:#X}, page fault error = {}", virtual_address, pferror);
 
@@ -654,6 +887,14 @@ pub fn map<S: PageSize>(virtual_address: usize, physical_address: usize, count:
 	root_pagetable.map_pages(range, physical_address, flags);
 }
 
+pub fn unmap<S: PageSize>(virtual_address: usize, count: usize) {
+	debug!("Unmapping virtual address {:#X} ({} pages)", virtual_address, count);
+
+	let range = get_page_range::<S>(virtual_address, count);
+	let root_pagetable = unsafe { &mut *PML4_ADDRESS };
+	root_pagetable.unmap_pages(range);
+}
+
 #[repr(align(0x1000))]
 #[repr(C)]
 struct KernelPageTables {
@@ -675,38 +916,157 @@ pub fn get_kernel_root_page_table() -> usize {
 		unsafe { &ROOT_PAGE_TABLES as *const _ as usize }
 }
 
+/// Remove the user pages [start, end) of the current address space
+/// and release their page frames
+pub fn unmap_user_pages(start: usize, end: usize) {
+	for page in (start..end).step_by(BasePageSize::SIZE) {
+		if let Some(entry) = get_page_table_entry::<BasePageSize>(page) {
+			unmap::<BasePageSize>(page, 1);
+
+			if entry.address() >= mm::kernel_end_address()
+				&& !entry.flags().contains(PageTableEntryFlags::UNMANAGED) {
+				physicalmem::release(entry.address());
+			}
+		}
+	}
+}
+
+/// Set the permissions of the existing user pages [start, end).
+/// Shared pages stay read-only and are copied on the next write access.
+pub fn protect_user_pages(start: usize, end: usize, flags: PageTableEntryFlags) {
+	for page in (start..end).step_by(BasePageSize::SIZE) {
+		if let Some(entry) = get_page_table_entry::<BasePageSize>(page) {
+			let address = entry.address();
+			let unmanaged = entry.flags().contains(PageTableEntryFlags::UNMANAGED);
+			let mut page_flags = flags;
+
+			if unmanaged {
+				page_flags.insert(PageTableEntryFlags::UNMANAGED);
+			}
+
+			if unmanaged || entry.flags().contains(PageTableEntryFlags::COPY_ON_WRITE)
+				|| (address >= mm::kernel_end_address() && physicalmem::is_shared(address)) {
+				if page_flags.contains(PageTableEntryFlags::WRITABLE) {
+					page_flags.remove(PageTableEntryFlags::WRITABLE);
+					page_flags.insert(PageTableEntryFlags::COPY_ON_WRITE);
+				}
+			}
+
+			map::<BasePageSize>(page, address, 1, page_flags);
+		}
+	}
+}
+
+/// Release all user-level pages and page tables of the current address space
 pub fn drop_user_space() {
 	let root_pagetable = unsafe { &mut *PML4_ADDRESS };
 
 	root_pagetable.drop_user_space();
+
+	// flush all non-global entries from the TLB
+	unsafe { controlregs::cr3_write(controlregs::cr3()); }
 }
 
-// just an workaround to explaine the difference between
-// kernel and user space
-pub fn create_usr_pgd() -> usize {
-	debug!("Create 1st level page table for the user-level task");
+/// Index of the PML4 entry, which maps the kernel space
+const PML4_KERNEL_INDEX: usize = 0;
 
-	unsafe {
-		let physical_address = physicalmem::allocate_aligned(BasePageSize::SIZE, BasePageSize::SIZE);
-		let user_page_table: usize = 0x10000; // it is always free...
-		let kernel_page_table = get_kernel_root_page_table();
+/// Index of the PML4 entry, which is used for the recursive mapping
+const PML4_SELF_INDEX: usize = (1 << PAGE_MAP_BITS) - 1;
+
+/// The page tables of a user-level process
+///
+/// The kernel space is shared by all address spaces, while the
+/// user space (starting at USER_SPACE_START) is private.
+pub struct AddressSpace {
+	/// Physical address of the root page table (PML4)
+	root: usize
+}
+
+impl AddressSpace {
+	/// Create an address space with an empty user space
+	pub fn new() -> Self {
+		let _preemption = scheduler::DisabledPreemption::new();
+		let root = physicalmem::allocate(BasePageSize::SIZE);
 
-		debug!("Map page frame 0x{:x} at virtual address 0x{:x}", physical_address, user_page_table);
+		debug!("Create address space with root page table 0x{:x}", root);
 
-		map::<BasePageSize>(user_page_table, physical_address, 1,
+		// The new root page table isn't part of the current page table hierarchy
+		// => map it temporarily into the kernel space
+		let virtual_address = virtualmem::allocate(BasePageSize::SIZE);
+		map::<BasePageSize>(virtual_address, root, 1,
 			PageTableEntryFlags::WRITABLE | PageTableEntryFlags::EXECUTE_DISABLE);
 
-		memset(user_page_table as *mut u8, 0x00, BasePageSize::SIZE);
+		unsafe {
+			memset(virtual_address as *mut u8, 0x00, BasePageSize::SIZE);
+
+			let kernel_pml4 = &*(get_kernel_root_page_table() as *const PageTable<PML4>);
+			let pml4 = &mut *(virtual_address as *mut PageTable<PML4>);
+
+			pml4.entries[PML4_KERNEL_INDEX] = kernel_pml4.entries[PML4_KERNEL_INDEX];
+			pml4.entries[PML4_SELF_INDEX].set(root, PageTableEntryFlags::WRITABLE);
+		}
+
+		unmap::<BasePageSize>(virtual_address, 1);
+		virtualmem::deallocate(virtual_address, BasePageSize::SIZE);
+
+		AddressSpace {
+			root: root
+		}
+	}
+
+	/// Create a copy of this address space, which has to be the current one.
+	/// The user pages are shared by both address spaces and writable pages
+	/// are copied on the first write access.
+	pub fn fork(&self) -> Self {
+		assert!(unsafe { controlregs::cr3() } as usize == self.root, "Only the current address space can be forked");
+
+		let child = AddressSpace::new();
+		let mut pages = Vec::new();
+		let _preemption = scheduler::DisabledPreemption::new();
+
+		let root_pagetable = unsafe { &mut *PML4_ADDRESS };
+		root_pagetable.share_user_space(0, &mut pages);
+
+		debug!("Share {} pages with address space 0x{:x}", pages.len(), child.root);
+
+		child.activate();
+		for &(virtual_address, entry) in pages.iter() {
+			map::<BasePageSize>(virtual_address, entry.address(), 1, entry.flags());
+		}
+
+		// reloading cr3 flushes also the writable pages of this address space from the TLB
+		self.activate();
+
+		child
+	}
+
+	/// Physical address of the root page table
+	pub fn root_page_table(&self) -> usize {
+		self.root
+	}
+
+	/// Load the page tables into the MMU
+	pub fn activate(&self) {
+		unsafe { controlregs::cr3_write(self.root as u64); }
+	}
+}
 
-		let pml4 = user_page_table as *mut PageTableEntry;
-		(*pml4).set(kernel_page_table + BasePageSize::SIZE, PageTableEntryFlags::WRITABLE
-			| PageTableEntryFlags::USER_ACCESSIBLE);
-		let pml4 = (user_page_table + BasePageSize::SIZE - size_of::<usize>()) as *mut PageTableEntry;
-		(*pml4).set(physical_address, PageTableEntryFlags::WRITABLE);
+impl Drop for AddressSpace {
+	fn drop(&mut self) {
+		debug!("Destroy address space with root page table 0x{:x}", self.root);
 
-		scheduler::set_root_page_table(physical_address);
+		// The recursive mapping only gives access to the current page tables
+		// => switch temporarily to this address space to release the user space
+		{
+			let _preemption = scheduler::DisabledPreemption::new();
+			let current = unsafe { controlregs::cr3() };
+
+			self.activate();
+			drop_user_space();
+			unsafe { controlregs::cr3_write(current); }
+		}
 
-		physical_address
+		physicalmem::deallocate(self.root, BasePageSize::SIZE);
 	}
 }
 
@@ -5,16 +5,91 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use arch::x86_64::mm::paging::{BasePageSize, PageSize};
-use arch::x86_64::kernel::get_memory_size;
-use collections::Node;
+use core::cmp;
+use arch::x86_64::mm::paging;
+use arch::x86_64::mm::paging::{BasePageSize, LargePageSize, PageSize, PageTableEntryFlags};
+use arch::x86_64::kernel::{get_memory_size,get_memfile,get_memory_map,MemoryRegionKind};
+use logging::*;
 use mm;
-use mm::freelist::{FreeList, FreeListEntry};
-use mm::POOL;
+use mm::buddy::BuddyAllocator;
 use scheduler::DisabledPreemption;
 
-static mut PHYSICAL_FREE_LIST: FreeList = FreeList::new();
+/// Maximum number of free memory regions
+const MAX_FREE_REGIONS: usize = 64;
 
+static mut PHYSICAL_ALLOCATOR: BuddyAllocator = BuddyAllocator::new();
+
+/// Free memory regions, detected at boot time
+static mut FREE_REGIONS: [(usize, usize); MAX_FREE_REGIONS] = [(0, 0); MAX_FREE_REGIONS];
+static mut NO_FREE_REGIONS: usize = 0;
+
+/// Physical (and virtual) address and size of the frame table
+static mut FRAME_TABLE: (usize, usize) = (0, 0);
+
+/// Add the region [start, end) to the list of free regions
+fn add_free_region(start: usize, end: usize) {
+	info!("Free memory region 0x{:x} - 0x{:x} ({} KByte)", start, end, (end - start) >> 10);
+
+	unsafe {
+		if NO_FREE_REGIONS < MAX_FREE_REGIONS {
+			FREE_REGIONS[NO_FREE_REGIONS] = (start, end);
+			NO_FREE_REGIONS += 1;
+		} else {
+			info!("Ignore memory region 0x{:x} - 0x{:x}, too many regions", start, end);
+		}
+	}
+}
+
+fn detect_from_memory_map() -> Result<(), ()> {
+	let map = get_memory_map();
+
+	if map.len() == 0 {
+		return Err(());
+	}
+
+	let (file_addr, file_len) = get_memfile();
+	let file_start = align_down!(file_addr as usize, BasePageSize::SIZE);
+	let file_end = align_up!((file_addr + file_len) as usize, BasePageSize::SIZE);
+	let mut found = false;
+
+	info!("Reserved memory region 0x0 - 0x{:x} (low memory and kernel)", mm::kernel_end_address());
+
+	// The memory map is sorted by the start addresses and
+	// consequently, the free regions are also sorted.
+	for region in map {
+		if region.kind != MemoryRegionKind::Available {
+			info!("Reserved memory region 0x{:x} - 0x{:x} ({:?})", region.start, region.end, region.kind);
+			continue;
+		}
+
+		let start = cmp::max(align_up!(region.start, BasePageSize::SIZE), mm::kernel_end_address());
+		let end = align_down!(region.end, BasePageSize::SIZE);
+
+		if start >= end {
+			continue;
+		}
+
+		if file_len > 0 && file_start < end && file_end > start {
+			if start < file_start {
+				add_free_region(start, file_start);
+			}
+			info!("Reserved memory region 0x{:x} - 0x{:x} (boot module)", file_start, file_end);
+			if file_end < end {
+				add_free_region(file_end, end);
+			}
+		} else {
+			add_free_region(start, end);
+		}
+
+		found = true;
+	}
+
+	if found {
+		Ok(())
+	} else {
+		Err(())
+	}
+}
 
 fn detect_from_limits() -> Result<(), ()> {
 	let limit = get_memory_size();
@@ -23,19 +98,48 @@ fn detect_from_limits() -> Result<(), ()> {
 		return Err(());
 	}
 
-	let entry = Node::new(
-		FreeListEntry {
-			start: mm::kernel_end_address(),
-			end: limit
-		}
-	);
-	unsafe { PHYSICAL_FREE_LIST.list.push(entry); }
+	add_free_region(mm::kernel_end_address(), limit);
 
 	Ok(())
 }
 
 pub fn init() {
-	detect_from_limits().unwrap();
+	detect_from_memory_map().or_else(|_| detect_from_limits()).unwrap();
+
+	let regions = unsafe { &FREE_REGIONS[0..NO_FREE_REGIONS] };
+	let start = mm::kernel_end_address();
+	let end = regions.iter().map(|r| r.1).max().unwrap();
+
+	// The frame table is located directly behind the kernel and identity-mapped
+	// with 2 MiB pages. The page tables of the first GiB already exist and
+	// consequently, the mapping doesn't require any physical memory.
+	let table_size = align_up!(BuddyAllocator::table_size(start, end), LargePageSize::SIZE);
+	assert!(regions[0].0 == start && regions[0].1 >= start + table_size,
+		"Unable to place the frame table behind the kernel");
+	assert!(start + table_size <= 0x40000000, "The frame table must be located in the first GiB");
+
+	info!("Frame table at 0x{:x} (size {} KByte)", start, table_size >> 10);
+
+	paging::map::<LargePageSize>(start, start, table_size / LargePageSize::SIZE,
+		PageTableEntryFlags::WRITABLE | PageTableEntryFlags::GLOBAL | PageTableEntryFlags::EXECUTE_DISABLE);
+
+	unsafe {
+		FRAME_TABLE = (start, table_size);
+		PHYSICAL_ALLOCATOR.init(start, start, end);
+
+		for &(region_start, region_end) in regions {
+			let region_start = cmp::max(region_start, start + table_size);
+
+			if region_start < region_end {
+				PHYSICAL_ALLOCATOR.add_region(region_start, region_end);
+			}
+		}
+	}
+}
+
+/// Returns the address and the size of the frame table
+pub fn get_frame_table() -> (usize, usize) {
+	unsafe { FRAME_TABLE }
 }
 
 pub fn allocate(size: usize) -> usize {
@@ -43,7 +147,7 @@ pub fn allocate(size: usize) -> usize {
 	assert!(size % BasePageSize::SIZE == 0, "Size {:#X} is not a multiple of {:#X}", size, BasePageSize::SIZE);
 
 	let _preemption = DisabledPreemption::new();
-	let result = unsafe { PHYSICAL_FREE_LIST.allocate(size) };
+	let result = unsafe { PHYSICAL_ALLOCATOR.allocate(size, BasePageSize::SIZE) };
 	assert!(result.is_ok(), "Could not allocate {:#X} bytes of physical memory", size);
 	result.unwrap()
 }
@@ -55,10 +159,7 @@ pub fn allocate_aligned(size: usize, alignment: usize) -> usize {
 	assert!(alignment % BasePageSize::SIZE == 0, "Alignment {:#X} is not a multiple of {:#X}", alignment, BasePageSize::SIZE);
 
 	let _preemption = DisabledPreemption::new();
-	let result = unsafe {
-		POOL.maintain();
-		PHYSICAL_FREE_LIST.allocate_aligned(size, alignment)
-	};
+	let result = unsafe { PHYSICAL_ALLOCATOR.allocate(size, alignment) };
 	assert!(result.is_ok(), "Could not allocate {:#X} bytes of physical memory aligned to {} bytes", size, alignment);
 	result.unwrap()
 }
@@ -70,7 +171,37 @@ pub fn deallocate(physical_address: usize, size: usize) {
 
 	let _preemption = DisabledPreemption::new();
 	unsafe {
-		POOL.maintain();
-		PHYSICAL_FREE_LIST.deallocate(physical_address, size);
+		PHYSICAL_ALLOCATOR.deallocate(physical_address, size);
+	}
+}
+
+/// Add a reference to a page frame, which is shared by several address spaces
+pub fn share(physical_address: usize) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		PHYSICAL_ALLOCATOR.share(physical_address);
+	}
+}
+
+/// Remove a reference to a page frame and release the frame,
+/// if it isn't referenced anymore
+pub fn release(physical_address: usize) {
+	assert!(physical_address >= mm::kernel_end_address(), "Physical address {:#X} is not >= KERNEL_END_ADDRESS", physical_address);
+
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		PHYSICAL_ALLOCATOR.release(physical_address);
 	}
 }
+
+/// Returns true, if the page frame is referenced by several address spaces
+pub fn is_shared(physical_address: usize) -> bool {
+	let _preemption = DisabledPreemption::new();
+	unsafe { PHYSICAL_ALLOCATOR.is_shared(physical_address) }
+}
+
+/// Print the usage of the physical memory
+pub fn print_statistics() {
+	let _preemption = DisabledPreemption::new();
+	unsafe { PHYSICAL_ALLOCATOR.print_statistics(); }
+}
@@ -0,0 +1,95 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Access to the user space from the kernel
+//!
+//! All copies between the kernel and the user space use `copy_user_bytes`.
+//! If the copy raises a page fault, which the page fault handler isn't able
+//! to resolve, the handler continues behind the copy instruction and the
+//! copy returns the number of remaining bytes. Consequently, an invalid
+//! user-space pointer doesn't crash the kernel.
+//!
+//! With SMAP, the copy is the only place, where the kernel is allowed to
+//! touch user-space pages. Every other access raises a page fault.
+
+use arch::x86_64::kernel::irq::ExceptionStackFrame;
+use arch::x86_64::kernel::processor::UserAccess;
+use consts::*;
+use errno::*;
+
+extern "C" {
+	/// Copy `len` bytes from `src` to `dst` and return the number of bytes,
+	/// which aren't copied because of a page fault
+	fn copy_user_bytes(dst: *mut u8, src: *const u8, len: usize) -> usize;
+
+	/// Address of the copy instruction, which may raise a page fault
+	static copy_user_fault: u8;
+	/// Address behind the copy instruction
+	static copy_user_fixup: u8;
+}
+
+global_asm!(r#"
+.section .text
+.global copy_user_bytes
+.global copy_user_fault
+.global copy_user_fixup
+copy_user_bytes:
+	mov %rdx, %rcx
+copy_user_fault:
+	rep movsb
+copy_user_fixup:
+	mov %rcx, %rax
+	ret
+"#);
+
+/// Checks if the range [addr, addr + len) is part of the user space
+pub fn is_user_range(addr: usize, len: usize) -> bool {
+	match addr.checked_add(len) {
+		Some(end) => addr >= USER_SPACE_START && end <= USER_SPACE_END,
+		None => false
+	}
+}
+
+/// Copy `dst.len()` bytes from the user-space address `src` into the kernel
+pub fn copy_from_user(dst: &mut [u8], src: usize) -> Result<()> {
+	if !is_user_range(src, dst.len()) {
+		return Err(Error::BadAddress);
+	}
+
+	let _access = UserAccess::new();
+	match unsafe { copy_user_bytes(dst.as_mut_ptr(), src as *const u8, dst.len()) } {
+		0 => Ok(()),
+		_ => Err(Error::BadAddress)
+	}
+}
+
+/// Copy `src` from the kernel to the user-space address `dst`
+pub fn copy_to_user(dst: usize, src: &[u8]) -> Result<()> {
+	if !is_user_range(dst, src.len()) {
+		return Err(Error::BadAddress);
+	}
+
+	let _access = UserAccess::new();
+	match unsafe { copy_user_bytes(dst as *mut u8, src.as_ptr(), src.len()) } {
+		0 => Ok(()),
+		_ => Err(Error::BadAddress)
+	}
+}
+
+/// Called by the page fault handler for faults, which can't be resolved.
+/// If the fault is raised by a copy from or to the user space, the copy
+/// is aborted and the function returns true.
+pub fn fixup_user_access(stack_frame: &mut ExceptionStackFrame) -> bool {
+	let fault = unsafe { &copy_user_fault as *const u8 as u64 };
+
+	if stack_frame.instruction_pointer == fault {
+		stack_frame.instruction_pointer = unsafe { &copy_user_fixup as *const u8 as u64 };
+		true
+	} else {
+		false
+	}
+}
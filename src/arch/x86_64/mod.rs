@@ -7,129 +7,610 @@
 
 pub mod kernel;
 pub mod mm;
+mod reloc;
 
 use alloc::string::String;
 use errno::*;
 use alloc::vec::Vec;
-use goblin::{elf,elf64};
-use goblin::elf::program_header::{PT_LOAD,PT_GNU_RELRO,PT_DYNAMIC};
-use goblin::elf64::dyn::{DT_RELA,DT_RELASZ};
-use goblin::elf64::reloc::{R_386_RELATIVE,R_386_GLOB_DAT};
-//use goblin::elf::header::{EM_X86_64,ET_EXEC};
-//use goblin::elf64::section_header::{SHT_RELA,SHT_REL};
+use goblin::elf;
+use goblin::elf::program_header::{PT_LOAD,PT_GNU_RELRO,PT_PHDR,PT_TLS};
+use goblin::elf::header::{ET_EXEC,ET_DYN};
 use logging::*;
 use fs;
 use consts::*;
-use self::mm::physicalmem;
 use self::mm::paging;
-use self::mm::paging::{BasePageSize,PageSize,PageTableEntryFlags};
+use self::mm::paging::{BasePageSize,PageSize};
 use compiler_builtins::mem::memset;
-use core::slice;
-use x86::controlregs;
+use core::{cmp,ptr};
+use core::mem::size_of;
+use scheduler;
+use self::kernel::processor;
+use self::kernel::vdso;
+use self::reloc::Relocation;
+use mm::vma::{VmaFile,VmaFlags,VmaTable};
+use fs::FileHandle;
+use alloc::rc::Rc;
+use alloc::boxed::Box;
 
-pub fn load_application(path: &String) -> Result<()> {
-	unsafe {
-		controlregs::cr3_write(paging::create_usr_pgd() as u64);
-	}
+// types of the entries in the auxiliary vector
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_BASE: u64 = 7;
+const AT_ENTRY: u64 = 9;
+const AT_RANDOM: u64 = 25;
+const AT_SYSINFO_EHDR: u64 = 33;
+
+/// Maximum size of the arguments and the environment on the initial stack
+const MAX_ARGUMENT_SIZE: usize = 0x20000;
+
+/// Number of random bytes, which are referenced by AT_RANDOM
+const RANDOM_BYTES: usize = 16;
 
-	let mut file = fs::open(path, fs::OpenOptions::READONLY)?;
+/// Size of the thread control block, which follows the initial TLS block
+const TCB_SIZE: usize = 64;
+
+/// Offset of the stack protector canary in the thread control block
+const TCB_STACK_GUARD: usize = 0x28;
+
+/// Read the executable `path` from the file system. The segments are
+/// mapped from the returned file handle, the buffer is only used to
+/// parse the executable.
+fn read_executable(path: &String) -> Result<(Rc<Box<FileHandle>>, Vec<u8>)> {
+	let file = fs::open(path, fs::OpenOptions::READONLY)?;
 	let len = file.len();
 	let mut buffer: Vec<u8> = Vec::new();
 
 	buffer.resize(len, 0);
-	file.read(&mut buffer)?;
-	let elf = match elf::Elf::parse(&buffer) {
-		Ok(n) => n,
-		_ => return Err(Error::InvalidArgument)
-	};
-    debug!("elf information: {:#?}", &elf);
+	if file.read_at(&mut buffer, 0)? != len {
+		return Err(Error::InvalidExecutable);
+	}
 
-	if elf.is_lib == false ||
+	Ok((Rc::new(file), buffer))
+}
+
+/// Placement of an executable in the user space
+struct ExecutableLayout {
+	/// Load bias, which is added to the virtual addresses of the executable
+	bias: usize,
+	/// Values, which have to be written after mapping the executable
+	relocations: Vec<Relocation>,
+	/// PT_GNU_RELRO becomes read-only after applying the relocations.
+	/// Static PIEs are an exception, because they write to PT_GNU_RELRO
+	/// after the start.
+	protect_relro: bool
+}
+
+/// Parse the ELF header and the program headers of an executable
+fn parse_executable(buffer: &[u8]) -> Result<elf::Elf> {
+	match elf::Elf::parse(buffer) {
+		Ok(n) => Ok(n),
+		_ => Err(Error::InvalidExecutable)
+	}
+}
+
+/// Check if the executable is supported and if its segments are valid.
+/// Returns the layout of the executable in the user space. `dynamic_linker`
+/// specifies, if the program is started by its dynamic linker.
+///
+/// The program is mapped between USER_SPACE_START and INTERPRETER_START,
+/// while the dynamic linker (`interpreter` is true) is mapped behind INTERPRETER_START.
+/// Position-independent executables (ET_DYN) are relocated to the begin of
+/// their region, while classic executables (ET_EXEC) are mapped at their link addresses.
+fn check_executable(elf: &elf::Elf, buffer: &[u8], interpreter: bool, dynamic_linker: bool) -> Result<ExecutableLayout> {
+	let len = buffer.len();
+
+	debug!("elf information: {:#?}", elf);
+
+	if (elf.header.e_type != ET_EXEC && elf.header.e_type != ET_DYN) ||
 	   elf.is_64 == false {
-		   return Err(Error::InvalidArgument);
+		   return Err(Error::InvalidExecutable);
 	}
 
-	if elf.libraries.len() > 0 {
-		error!("Error: file depends on following libraries: {:?}", elf.libraries);
-		return Err(Error::InvalidArgument);
+	if interpreter {
+		if elf.header.e_type != ET_DYN || elf.interpreter.is_some() {
+			error!("Error: invalid dynamic linker");
+			return Err(Error::InvalidExecutable);
+		}
+	} else if elf.libraries.len() > 0 && elf.interpreter.is_none() {
+		error!("Error: file depends on following libraries, but doesn't define a dynamic linker: {:?}",
+			elf.libraries);
+		return Err(Error::InvalidExecutable);
 	}
 
 	// Determine the memory size of the executable
-	let vstart: usize = 0;
+	let mut vstart: Option<usize> = None;
 	let mut exec_size: usize = 0;
+	let mut previous: Option<&elf::ProgramHeader> = None;
 	for i in &elf.program_headers {
 		if i.p_type == PT_LOAD {
-			/*if vstart == 0 {
-				vstart = i.p_vaddr as usize;
-			}*/
+			if i.p_filesz > i.p_memsz || i.p_offset + i.p_filesz > len as u64 {
+				error!("Error: invalid segment at 0x{:x}", i.p_vaddr);
+				return Err(Error::InvalidExecutable);
+			}
+
+			// the segment is mapped page by page from the file
+			if i.p_offset % BasePageSize::SIZE as u64 != i.p_vaddr % BasePageSize::SIZE as u64 {
+				error!("Error: segment at 0x{:x} isn't aligned to its file offset", i.p_vaddr);
+				return Err(Error::InvalidExecutable);
+			}
+
+			// W^X: a page is either writable or executable
+			if i.is_write() && i.is_executable() {
+				error!("Error: segment at 0x{:x} is writable and executable", i.p_vaddr);
+				return Err(Error::InvalidExecutable);
+			}
 
-			exec_size = align_up!(i.p_vaddr as usize - vstart + i.p_memsz as usize, BasePageSize::SIZE);
+			if let Some(p) = previous {
+				if i.p_vaddr < p.p_vaddr + p.p_memsz {
+					error!("Error: segments aren't sorted or overlap");
+					return Err(Error::InvalidExecutable);
+				}
+
+				// segments with different permissions must not share a page
+				let shared = align_down!(i.p_vaddr as usize, BasePageSize::SIZE)
+					< align_up!((p.p_vaddr + p.p_memsz) as usize, BasePageSize::SIZE);
+				if shared && segment_flags(i) != segment_flags(p) {
+					error!("Error: segments at 0x{:x} and 0x{:x} share a page", p.p_vaddr, i.p_vaddr);
+					return Err(Error::InvalidExecutable);
+				}
+			}
+
+			let start = *vstart.get_or_insert(align_down!(i.p_vaddr as usize, BasePageSize::SIZE));
+			exec_size = align_up!(i.p_vaddr as usize - start + i.p_memsz as usize, BasePageSize::SIZE);
+			previous = Some(i);
+		} else if i.p_type == PT_TLS {
+			if i.p_filesz > i.p_memsz || i.p_offset + i.p_filesz > len as u64 {
+				error!("Error: invalid TLS segment at 0x{:x}", i.p_vaddr);
+				return Err(Error::InvalidExecutable);
+			}
 		}
 	}
+
+	let vstart = match vstart {
+		Some(vstart) => vstart,
+		None => {
+			error!("Error: unable to find PT_LOAD",);
+			return Err(Error::InvalidExecutable);
+		}
+	};
 	debug!("Virtual start address 0x{:x}", vstart);
-	debug!("Memory size 0x{:x} {}", exec_size, len);
+	debug!("Memory size 0x{:x}", exec_size);
+
+	let (region_start, region_end) = if interpreter {
+		(INTERPRETER_START, USER_STACK - MAX_USER_STACK_SIZE)
+	} else {
+		(USER_SPACE_START, INTERPRETER_START)
+	};
 
-	if exec_size == 0 {
-		error!("Error: unable to find PT_LOAD",);
-		return Err(Error::InvalidArgument);
+	// position-independent executables are moved to the begin of their region
+	let bias = if elf.header.e_type == ET_DYN && vstart < USER_SPACE_START {
+		region_start - vstart
+	} else {
+		0
+	};
+
+	// The kernel is linked at 2 MiB and owns the first PML4 entry. Consequently,
+	// classic executables, which are linked at the default address 0x400000,
+	// overlap the kernel space and can't be loaded.
+	if elf.header.e_type == ET_EXEC && vstart < USER_SPACE_START {
+		error!("Error: ET_EXEC binary is linked at 0x{:x}, but the user space starts at 0x{:x}",
+			vstart, USER_SPACE_START);
+		return Err(Error::InvalidExecutable);
 	}
 
-	let physical_address = physicalmem::allocate(exec_size);
-	paging::map::<BasePageSize>(USER_SPACE_START,
-		physical_address, exec_size / BasePageSize::SIZE,
-		PageTableEntryFlags::WRITABLE | PageTableEntryFlags::USER_ACCESSIBLE);
-	unsafe { memset(USER_SPACE_START as *mut u8, 0x00, exec_size); }
+	// the image has to be located in its region of the user space
+	let start = bias.checked_add(vstart);
+	let end = start.and_then(|s| s.checked_add(exec_size));
+	match (start, end) {
+		(Some(start), Some(end)) if start >= region_start && end <= region_end => {},
+		_ => {
+			error!("Error: executable at 0x{:x} (size 0x{:x}) isn't located in the user space", vstart, exec_size);
+			return Err(Error::InvalidExecutable);
+		}
+	}
+
+	// Only position-independent executables have to be relocated. If the
+	// program is started by its dynamic linker, the dynamic linker relocates
+	// the program and itself.
+	let relocate = elf.header.e_type == ET_DYN && !interpreter && !dynamic_linker;
+
+	// The startup code of static PIEs (musl's rcrt1.o, glibc's
+	// _dl_relocate_static_pie) relocates the executable again. It writes
+	// to PT_GNU_RELRO, which has to stay writable, and applies the packed
+	// relative and the IRELATIVE relocations itself.
+	//
+	// Consequently, the kernel can't protect PT_GNU_RELRO of static PIEs.
+	// glibc protects it after the relocation, but musl leaves it writable.
+	let self_relocating = elf.interpreter.is_none();
+
+	let relocations = if relocate {
+		reloc::relocations(elf, buffer, bias, self_relocating)?
+	} else {
+		Vec::new()
+	};
+
+	Ok(ExecutableLayout {
+		bias: bias,
+		relocations: relocations,
+		protect_relro: relocate && !self_relocating
+	})
+}
+
+/// Derive the allowed accesses from the permissions of a segment
+fn segment_flags(segment: &elf::ProgramHeader) -> VmaFlags {
+	let mut flags = VmaFlags::empty();
+
+	if segment.is_read() {
+		flags.insert(VmaFlags::READ);
+	}
+	if segment.is_write() {
+		flags.insert(VmaFlags::WRITE);
+	}
+	if segment.is_executable() {
+		flags.insert(VmaFlags::EXECUTE);
+	}
+
+	flags
+}
+
+/// Set the final permissions of the range [start, end) of an executable
+fn protect_area(start: usize, end: usize, flags: VmaFlags) {
+	if scheduler::protect_vma(start, end, flags).is_ok() {
+		paging::protect_user_pages(start, end, flags.page_flags());
+	}
+}
+
+/// Add the virtual memory areas of the executable's segments to `vmas`
+/// and return the end of the executable. The segments are read from
+/// `file` on demand and are writable until `map_executable` sets the
+/// final permissions.
+fn add_executable_areas(vmas: &mut VmaTable, elf: &elf::Elf, layout: &ExecutableLayout,
+	file: &Rc<Box<FileHandle>>) -> usize {
+	let mut image_end = 0;
+
+	for i in &elf.program_headers {
+		if i.p_type == PT_LOAD {
+			let start = layout.bias + i.p_vaddr as usize;
+			let end = start + i.p_memsz as usize;
+
+			// a page, which is shared with the previous segment, belongs to its area
+			let area_start = cmp::max(align_down!(start, BasePageSize::SIZE), image_end);
+			let area_end = align_up!(end, BasePageSize::SIZE);
+
+			if area_start < area_end {
+				let backing = VmaFile {
+					handle: file.clone(),
+					offset: i.p_offset as usize + area_start - start,
+					size: (start + i.p_filesz as usize).saturating_sub(area_start)
+				};
+
+				vmas.map(area_start, area_end, segment_flags(i) | VmaFlags::WRITE, Some(backing));
+				image_end = area_end;
+			}
+		}
+	}
+
+	image_end
+}
+
+/// Complete the executable in the current address space, apply
+/// the relocations and return its entry point
+fn map_executable(buffer: &[u8], elf: &elf::Elf, layout: &ExecutableLayout) -> u64 {
+	let bias = layout.bias;
+	let mut previous_end = 0;
 
-	let mut rela_addr: u64 = 0;
-	let mut relasz: u64 = 0;
-	//let mut relaent: u64 = 0;
 	for i in &elf.program_headers {
 		if i.p_type == PT_LOAD {
-			debug!("Load code for address 0x{:x}", i.p_vaddr);
-
-			let mem = (USER_SPACE_START + i.p_vaddr as usize - vstart) as *mut u8;
-			let mem_slice = unsafe { slice::from_raw_parts_mut(mem, i.p_filesz as usize) };
-
-			mem_slice[0..i.p_filesz as usize].clone_from_slice(
-				&buffer[(i.p_offset as usize)..(i.p_offset+i.p_filesz) as usize]
-			);
-		} else if i.p_type == PT_GNU_RELRO {
-			debug!("PT_GNU_RELRO at 0x{:x} (size 0x{:x})", i.p_vaddr, i.p_filesz);
-		} else if i.p_type == PT_DYNAMIC {
-			debug!("PT_DYNAMIC at 0x{:x} (size 0x{:x})", i.p_vaddr, i.p_filesz);
-
-			let mem = (USER_SPACE_START + i.p_vaddr as usize - vstart) as *mut u8;
-			let dyn = unsafe { elf::dyn::dyn64::from_raw(0, mem as usize) };
-
-			for j in dyn {
-				if j.d_tag == DT_RELA {
-					rela_addr = USER_SPACE_START as u64 + j.d_val;
-				} else if j.d_tag == DT_RELASZ {
-					relasz = j.d_val;
-				} /*else if j.d_tag == DT_RELAENT {
-					relaent = j.d_val;
-				}*/
+			let start = bias + i.p_vaddr as usize;
+			let end = start + i.p_memsz as usize;
+
+			debug!("Load segment at 0x{:x} - 0x{:x} (flags 0x{:x})", start, end, i.p_flags);
+
+			// the area of the previous segment contains the begin of this segment
+			let shared_end = cmp::min(start + i.p_filesz as usize, previous_end);
+			if start < shared_end {
+				unsafe {
+					let _access = processor::UserAccess::new();
+					ptr::copy_nonoverlapping(buffer[i.p_offset as usize..].as_ptr(), start as *mut u8,
+						shared_end - start);
+				}
 			}
+
+			previous_end = cmp::max(previous_end, align_up!(end, BasePageSize::SIZE));
+		}
+	}
+
+	for i in &layout.relocations {
+		unsafe {
+			let _access = processor::UserAccess::new();
+			*(i.address as *mut u64) = i.value;
+		}
+	}
+
+	// the relocations are applied => set the final permissions
+	for i in &elf.program_headers {
+		if i.p_type == PT_LOAD {
+			let start = bias + i.p_vaddr as usize;
+			let end = start + i.p_memsz as usize;
+
+			protect_area(align_down!(start, BasePageSize::SIZE), align_up!(end, BasePageSize::SIZE),
+				segment_flags(i));
 		}
 	}
 
-	let rela = unsafe { elf64::reloc::from_raw_rela(rela_addr as *const elf64::reloc::Rela,
-		relasz as usize) };
-	for j in rela {
-		let offset = (USER_SPACE_START - vstart + j.r_offset as usize) as *mut u64;
+	// the data after the relocation (e.g. the GOT) becomes read-only
+	for i in &elf.program_headers {
+		if i.p_type == PT_GNU_RELRO && layout.protect_relro {
+			let start = bias + i.p_vaddr as usize;
+			let end = start + i.p_memsz as usize;
+
+			debug!("PT_GNU_RELRO at 0x{:x} - 0x{:x}", start, end);
 
-		if (j.r_info & 0xF) == R_386_RELATIVE as u64 {
-			unsafe {
-				*offset = (USER_SPACE_START as i64 - vstart as i64 + j.r_addend) as u64;
+			// only pages, which are completely covered, are protected
+			if align_down!(start, BasePageSize::SIZE) < align_down!(end, BasePageSize::SIZE) {
+				protect_area(align_down!(start, BasePageSize::SIZE), align_down!(end, BasePageSize::SIZE),
+					VmaFlags::READ);
 			}
-		} else if (j.r_info & 0xF) == R_386_GLOB_DAT as u64 {
-		} else {
-			error!("Unsupported relocation type {}", j.r_info & 0xF);
 		}
 	}
 
-	let entry = elf.entry - vstart as u64 + USER_SPACE_START as u64;
+	elf.entry + bias as u64
+}
+
+/// Determine the address of the program headers in the memory image
+fn program_headers_address(elf: &elf::Elf, base: u64) -> u64 {
+	for i in &elf.program_headers {
+		if i.p_type == PT_PHDR {
+			return base + i.p_vaddr;
+		}
+	}
+
+	// without PT_PHDR, the program headers have to be part of a loaded segment
+	let offset = elf.header.e_phoff;
+	for i in &elf.program_headers {
+		if i.p_type == PT_LOAD && offset >= i.p_offset && offset < i.p_offset + i.p_filesz {
+			return base + i.p_vaddr + offset - i.p_offset;
+		}
+	}
+
+	0
+}
+
+/// Check if the arguments and the environment fit on the initial stack.
+/// Like Linux, they may use at most a quarter of the stack limit.
+fn check_arguments(argv: &[String], envp: &[String]) -> Result<()> {
+	let size = argv.iter().chain(envp.iter())
+		.fold(0, |acc, s| acc + s.len() + 1 + size_of::<u64>());
+	let (stack_limit, _) = scheduler::get_stack_limit();
+
+	if size > cmp::min(MAX_ARGUMENT_SIZE, stack_limit / 4) {
+		Err(Error::ArgumentListTooLong)
+	} else {
+		Ok(())
+	}
+}
+
+/// Push `data` on the user-level stack and return its address
+unsafe fn push_bytes(sp: &mut usize, data: &[u8]) -> usize {
+	let _access = processor::UserAccess::new();
+
+	*sp -= data.len();
+	ptr::copy_nonoverlapping(data.as_ptr(), *sp as *mut u8, data.len());
+	*sp
+}
+
+/// Push a null-terminated copy of `s` on the user-level stack and return its address
+unsafe fn push_string(sp: &mut usize, s: &String) -> usize {
+	push_bytes(sp, &[0u8]);
+	push_bytes(sp, s.as_bytes())
+}
+
+/// Create the initial stack of a process as defined by the System V ABI
+/// and return the initial stack pointer.
+///
+/// From the stack pointer upwards, the stack contains argc, the argv pointers,
+/// a null pointer, the envp pointers, a null pointer, the auxiliary vector
+/// and finally, the strings and random bytes, which are referenced by the vectors.
+fn create_initial_stack(mut sp: usize, argv: &[String], envp: &[String], auxv: &[(u64, u64)]) -> u64 {
+	unsafe {
+		let mut random = [0u8; RANDOM_BYTES];
+		for chunk in random.chunks_mut(size_of::<u64>()) {
+			let value = processor::get_random();
+			chunk.copy_from_slice(&value.to_ne_bytes()[0..chunk.len()]);
+		}
+		let random_address = push_bytes(&mut sp, &random);
+
+		let envp_addresses: Vec<usize> = envp.iter().map(|s| push_string(&mut sp, s)).collect();
+		let argv_addresses: Vec<usize> = argv.iter().map(|s| push_string(&mut sp, s)).collect();
+
+		let mut vector: Vec<u64> = Vec::new();
+		vector.push(argv.len() as u64);
+		vector.extend(argv_addresses.iter().map(|a| *a as u64));
+		vector.push(0);
+		vector.extend(envp_addresses.iter().map(|a| *a as u64));
+		vector.push(0);
+		for &(key, value) in auxv {
+			vector.push(key);
+			vector.push(value);
+		}
+		vector.push(AT_RANDOM);
+		vector.push(random_address as u64);
+		vector.push(AT_NULL);
+		vector.push(0);
+
+		// the stack pointer has to be 16 byte aligned at the process entry
+		sp = align_down!(sp - vector.len() * size_of::<u64>(), 16);
+		let _access = processor::UserAccess::new();
+		ptr::copy_nonoverlapping(vector.as_ptr(), sp as *mut u64, vector.len());
+	}
+
+	sp as u64
+}
+
+/// Create the initial TLS block of the program on top of the user-level stack
+/// and return the thread pointer, which is used as FS base.
+///
+/// x86_64 uses the TLS variant II. The TLS block is located in front of the
+/// thread pointer and the thread pointer references the thread control block,
+/// whose first entry points to itself.
+fn create_tls(buffer: &[u8], elf: &elf::Elf, sp: &mut usize) -> usize {
+	let tls = match elf.program_headers.iter().find(|i| i.p_type == PT_TLS) {
+		Some(tls) => tls,
+		None => return 0
+	};
+	// the initialization image is located at the end of the TLS block
+	let offset = align_up!(tls.p_memsz as usize, cmp::max(tls.p_align as usize, 1));
+
+	debug!("PT_TLS at 0x{:x} (size 0x{:x}, alignment 0x{:x})", tls.p_vaddr, tls.p_memsz, tls.p_align);
+
+	let thread_pointer = align_down!(*sp - TCB_SIZE, cmp::max(tls.p_align as usize, 16));
+	let block = thread_pointer - offset;
+	*sp = align_down!(block, 16);
+
+	unsafe {
+		let _access = processor::UserAccess::new();
+		memset(block as *mut u8, 0x00, offset + TCB_SIZE);
+		ptr::copy_nonoverlapping(buffer[tls.p_offset as usize..].as_ptr(), block as *mut u8,
+			tls.p_filesz as usize);
+		*(thread_pointer as *mut usize) = thread_pointer;
+		*((thread_pointer + TCB_STACK_GUARD) as *mut u64) = processor::get_random();
+	}
+
+	thread_pointer
+}
+
+/// Read the dynamic linker, which is requested by the program with PT_INTERP.
+///
+/// A program without shared libraries (e.g. built with `musl-gcc -pie`) doesn't
+/// need its dynamic linker. If the dynamic linker isn't available, the kernel
+/// relocates such a program itself.
+fn read_interpreter(elf: &elf::Elf) -> Result<Option<(Rc<Box<FileHandle>>, Vec<u8>)>> {
+	match elf.interpreter {
+		Some(path) => {
+			debug!("Program requires the dynamic linker {}", path);
+			match read_executable(&String::from(path)) {
+				Ok(interpreter) => Ok(Some(interpreter)),
+				Err(_) if elf.libraries.len() == 0 => {
+					info!("Dynamic linker {} isn't available, start the program without it", path);
+					Ok(None)
+				},
+				Err(e) => {
+					error!("Error: unable to read the dynamic linker {}", path);
+					Err(e)
+				}
+			}
+		},
+		None => Ok(None)
+	}
+}
+
+/// Read and check the executable `path` and its dynamic linker. Afterwards,
+/// `prepare` is called to create an empty user space, the executable is
+/// mapped into the user space and the initial stack is created.
+/// Returns the entry point and the initial stack pointer.
+fn load_executable<F: FnOnce()>(path: &String, argv: &[String], envp: &[String], prepare: F) -> Result<(u64, u64)> {
+	let (file, buffer) = read_executable(path)?;
+	let elf = parse_executable(&buffer)?;
+	let interpreter_file = read_interpreter(&elf)?;
+	let layout = check_executable(&elf, &buffer, false, interpreter_file.is_some())?;
+
+	let interpreter = match interpreter_file {
+		Some((ref file, ref buffer)) => {
+			let elf = parse_executable(buffer)?;
+			let layout = check_executable(&elf, buffer, true, false)?;
+			Some((file, buffer, elf, layout))
+		},
+		None => None
+	};
+
+	// the heap starts behind the program, the stack below USER_STACK
+	// grows on demand up to the stack limit of the task
+	let (stack_limit, stack_limit_max) = scheduler::get_stack_limit();
+	let mut vmas = VmaTable::new();
+	vmas.set_stack_limit(stack_limit, stack_limit_max)?;
+	let image_end = add_executable_areas(&mut vmas, &elf, &layout, &file);
+	vmas.set_heap(image_end);
+	if let Some((file, _, ref elf, ref layout)) = interpreter {
+		add_executable_areas(&mut vmas, elf, layout, file);
+	}
+	vmas.map(USER_STACK - BasePageSize::SIZE, USER_STACK,
+		VmaFlags::READ | VmaFlags::WRITE | VmaFlags::GROWS_DOWN, None);
+
+	// the vDSO consists of the read-only data page and the executable image,
+	// which are special areas and, consequently, can't be resized
+	let vdso_size = vdso::get_vdso_size();
+	let vdso_address = vmas.map_anywhere(0, vdso_size, VmaFlags::READ | VmaFlags::SPECIAL, None)?;
+	vmas.protect(vdso_address + BasePageSize::SIZE, vdso_address + vdso_size,
+		VmaFlags::READ | VmaFlags::EXECUTE)?;
+
+	debug!("Execute {} (arguments {:?}, environment {:?})", path, argv, envp);
+
+	prepare();
+	scheduler::set_vmas(vmas);
+	let vdso_image = vdso::map_vdso(vdso_address);
+
+	let entry = map_executable(&buffer, &elf, &layout);
+	let mut auxv = [
+		(AT_PHDR, program_headers_address(&elf, layout.bias as u64)),
+		(AT_PHENT, elf.header.e_phentsize as u64),
+		(AT_PHNUM, elf.header.e_phnum as u64),
+		(AT_PAGESZ, BasePageSize::SIZE as u64),
+		(AT_ENTRY, entry),
+		(AT_SYSINFO_EHDR, vdso_image as u64)
+	].to_vec();
+
+	// the process starts in the dynamic linker, which loads the libraries
+	// and jumps afterwards to the entry point of the program
+	let start = match interpreter {
+		Some((_, buffer, ref elf, ref layout)) => {
+			auxv.push((AT_BASE, layout.bias as u64));
+			map_executable(buffer, elf, layout)
+		},
+		None => entry
+	};
+
+	// the old FS and GS base of the task are invalid in the new user space
+	let mut sp = USER_STACK;
+	processor::writefs(create_tls(&buffer, &elf, &mut sp));
+	processor::write_user_gs(0);
+
+	let stack = create_initial_stack(sp, argv, envp, &auxv);
+
+	Ok((start, stack))
+}
+
+pub fn load_application(path: &String) -> Result<()> {
+	let argv = [path.clone()];
+	let (entry, stack) = load_executable(path, &argv, &[], || {
+		scheduler::set_address_space(paging::AddressSpace::new());
+	})?;
+	drop(argv);
+
+	debug!("jump to user land at 0x{:x}", entry);
+	self::kernel::jump_to_user_land(entry, stack);
+}
+
+/// Replace the user space of the current task by the executable `path`.
+///
+/// The executable is read and checked before the user space is released.
+/// Consequently, the function returns only if the executable can't be loaded
+/// and the task is able to continue with its old user space.
+pub fn execve(path: String, argv: Vec<String>, envp: Vec<String>) -> Result<()> {
+	check_arguments(&argv, &envp)?;
+
+	// the old user space is released after checking the executable
+	let (entry, stack) = load_executable(&path, &argv, &envp, || {
+		paging::drop_user_space();
+	})?;
+
+	// jump_to_user_land doesn't return => release the arguments explicitly
+	drop(path);
+	drop(argv);
+	drop(envp);
 
 	debug!("jump to user land at 0x{:x}", entry);
-	self::kernel::jump_to_user_land(entry);
+	self::kernel::jump_to_user_land(entry, stack);
 }
@@ -0,0 +1,223 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Relocation of position-independent executables
+//!
+//! The relocations are determined from the file before the old user space
+//! is released. Consequently, an unsupported relocation doesn't leave the
+//! task in a half-destroyed state.
+//!
+//! The startup code of static PIEs, which are built with musl
+//! (`musl-gcc -static-pie`) or glibc (`gcc -static-pie`), relocates the
+//! executable a second time. Writing the RELA relocations again yields the
+//! same values, but the packed relative relocations add the load bias to
+//! the stored value. Consequently, they and the IRELATIVE relocations are
+//! left to such self-relocating executables.
+
+use alloc::vec::Vec;
+use core::mem::size_of;
+use goblin::elf;
+use goblin::elf::program_header::PT_LOAD;
+use goblin::elf::reloc::{R_X86_64_NONE,R_X86_64_64,R_X86_64_GLOB_DAT,R_X86_64_JUMP_SLOT,
+	R_X86_64_RELATIVE,R_X86_64_IRELATIVE};
+use goblin::elf::section_header::SHN_UNDEF;
+use goblin::elf::sym::STB_WEAK;
+use errno::*;
+use logging::*;
+
+// dynamic tags of the packed relative relocations (unknown to goblin)
+const DT_RELRSZ: u64 = 35;
+const DT_RELR: u64 = 36;
+const DT_RELRENT: u64 = 37;
+
+/// A 64 bit value, which has to be written after the executable is mapped
+pub struct Relocation {
+	/// Relocated address of the value
+	pub address: usize,
+	/// Relocated value
+	pub value: u64
+}
+
+/// Determine the offset in the file of the virtual address `vaddr`.
+/// Returns Some(None), if the address belongs to the uninitialized part
+/// of a segment, and None, if the address doesn't belong to a segment.
+fn file_offset(elf: &elf::Elf, vaddr: u64) -> Option<Option<usize>> {
+	for i in &elf.program_headers {
+		if i.p_type == PT_LOAD && vaddr >= i.p_vaddr && vaddr + size_of::<u64>() as u64 <= i.p_vaddr + i.p_memsz {
+			if vaddr + size_of::<u64>() as u64 <= i.p_vaddr + i.p_filesz {
+				return Some(Some((i.p_offset + vaddr - i.p_vaddr) as usize));
+			} else {
+				return Some(None);
+			}
+		}
+	}
+
+	None
+}
+
+/// Read the unrelocated 64 bit value at the virtual address `vaddr`
+fn read_u64(elf: &elf::Elf, buffer: &[u8], vaddr: u64) -> Result<u64> {
+	match file_offset(elf, vaddr) {
+		Some(Some(offset)) => {
+			let mut bytes = [0u8; 8];
+			bytes.copy_from_slice(&buffer[offset..offset + size_of::<u64>()]);
+			Ok(u64::from_le_bytes(bytes))
+		},
+		Some(None) => Ok(0),
+		None => {
+			error!("Error: address 0x{:x} isn't part of a segment", vaddr);
+			Err(Error::InvalidExecutable)
+		}
+	}
+}
+
+/// Check if the relocated value at `vaddr` belongs to a segment
+fn check_target(elf: &elf::Elf, vaddr: u64) -> Result<()> {
+	match file_offset(elf, vaddr) {
+		Some(_) => Ok(()),
+		None => {
+			error!("Error: relocation at 0x{:x} is outside of the segments", vaddr);
+			Err(Error::InvalidExecutable)
+		}
+	}
+}
+
+/// Determine the value of the dynamic symbol `index`. Only symbols, which
+/// are defined by the executable itself, can be resolved.
+fn symbol_value(elf: &elf::Elf, index: usize, bias: usize) -> Result<u64> {
+	let sym = match elf.dynsyms.get(index) {
+		Some(sym) => sym,
+		None => {
+			error!("Error: invalid symbol index {}", index);
+			return Err(Error::InvalidExecutable);
+		}
+	};
+
+	if sym.st_shndx == SHN_UNDEF as usize {
+		if sym.st_bind() == STB_WEAK {
+			// unresolved weak symbols are null
+			Ok(0)
+		} else {
+			error!("Error: undefined symbol {}", &elf.dynstrtab[sym.st_name]);
+			Err(Error::InvalidExecutable)
+		}
+	} else {
+		Ok(bias as u64 + sym.st_value)
+	}
+}
+
+/// Decode the packed relative relocations (DT_RELR) and return the
+/// unrelocated addresses, which have to be relocated
+fn relr_addresses(elf: &elf::Elf, buffer: &[u8], table: u64, size: u64) -> Result<Vec<u64>> {
+	let mut addresses = Vec::new();
+	let mut next: u64 = 0;
+
+	for i in 0..size / size_of::<u64>() as u64 {
+		let entry = read_u64(elf, buffer, table + i * size_of::<u64>() as u64)?;
+
+		if entry & 1 == 0 {
+			// an address, which is followed by bitmaps of the subsequent words
+			addresses.push(entry);
+			next = entry + size_of::<u64>() as u64;
+		} else {
+			// a bitmap of the 63 words behind the previous address or bitmap
+			let mut bitmap = entry >> 1;
+			let mut address = next;
+
+			while bitmap != 0 {
+				if bitmap & 1 != 0 {
+					addresses.push(address);
+				}
+				bitmap >>= 1;
+				address += size_of::<u64>() as u64;
+			}
+
+			next += 63 * size_of::<u64>() as u64;
+		}
+	}
+
+	Ok(addresses)
+}
+
+/// Determine all relocations of a position-independent executable,
+/// which is mapped with the load bias `bias`. If the executable is
+/// `self_relocating`, its startup code resolves the IRELATIVE and
+/// the packed relative relocations.
+pub fn relocations(elf: &elf::Elf, buffer: &[u8], bias: usize, self_relocating: bool) -> Result<Vec<Relocation>> {
+	let mut relocations = Vec::new();
+
+	if elf.dynrels.len() > 0 {
+		error!("Error: REL relocations aren't supported on x86_64");
+		return Err(Error::InvalidExecutable);
+	}
+
+	for reloc in elf.dynrelas.iter().chain(elf.pltrelocs.iter()) {
+		let vaddr = reloc.r_offset as u64;
+		let addend = reloc.r_addend as i64;
+
+		let value = match reloc.r_type {
+			R_X86_64_NONE => continue,
+			R_X86_64_RELATIVE => (bias as i64 + addend) as u64,
+			R_X86_64_64 => (symbol_value(elf, reloc.r_sym, bias)? as i64 + addend) as u64,
+			R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => symbol_value(elf, reloc.r_sym, bias)?,
+			R_X86_64_IRELATIVE => {
+				// the resolver is user code and can't be called by the kernel
+				if self_relocating {
+					debug!("Skip IRELATIVE relocation at 0x{:x}", vaddr);
+					continue;
+				} else {
+					error!("Error: IRELATIVE relocation at 0x{:x} isn't supported", vaddr);
+					return Err(Error::InvalidExecutable);
+				}
+			},
+			r_type => {
+				error!("Error: unsupported relocation type {} at 0x{:x}", r_type, vaddr);
+				return Err(Error::InvalidExecutable);
+			}
+		};
+
+		check_target(elf, vaddr)?;
+		relocations.push(Relocation {
+			address: bias + vaddr as usize,
+			value: value
+		});
+	}
+
+	// packed relative relocations
+	if let Some(ref dynamic) = elf.dynamic {
+		let mut relr: u64 = 0;
+		let mut relrsz: u64 = 0;
+
+		for d in &dynamic.dyns {
+			match d.d_tag {
+				DT_RELR => relr = d.d_val,
+				DT_RELRSZ => relrsz = d.d_val,
+				DT_RELRENT => if d.d_val != size_of::<u64>() as u64 {
+					error!("Error: invalid size of DT_RELR entries");
+					return Err(Error::InvalidExecutable);
+				},
+				_ => {}
+			}
+		}
+
+		if relr != 0 && !self_relocating {
+			for vaddr in relr_addresses(elf, buffer, relr, relrsz)? {
+				// the addend is stored at the relocated address
+				let value = read_u64(elf, buffer, vaddr)?.wrapping_add(bias as u64);
+
+				relocations.push(Relocation {
+					address: bias + vaddr as usize,
+					value: value
+				});
+			}
+		}
+	}
+
+	debug!("Executable requires {} relocations", relocations.len());
+
+	Ok(relocations)
+}
@@ -18,11 +18,26 @@ pub const CACHE_LINE : usize = 64;
 /// Maximum number of priorities
 pub const NO_PRIORITIES: usize = 32;
 
+/// Maximum number of open files per task
+pub const MAX_FILE_DESCRIPTORS: usize = 256;
+
 /// frequency of the timer interrupt
 pub const TIMER_FREQ: u32 = 100; /* in HZ */
 
 /// Start address of the user space
 pub const USER_SPACE_START: usize = 0x8000000000usize;
 
+/// First address behind the user space (end of the canonical lower half)
+pub const USER_SPACE_END: usize = 0x800000000000usize;
+
+/// Start address of the dynamic linker
+pub const INTERPRETER_START: usize = USER_SPACE_START + 0x400000000;
+
 /// Initial value of the stack pointer
 pub const USER_STACK: usize = USER_SPACE_START + 0x800000000;
+
+/// Maximum size of the user-level stack
+pub const MAX_USER_STACK_SIZE: usize = 0x800000;
+
+/// Lowest address of memory mappings, which doesn't have a fixed address
+pub const MMAP_START: usize = USER_SPACE_START + 0x1000000000;
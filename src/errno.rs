@@ -21,6 +21,99 @@ pub enum Error {
 	BadFsPermission,
 	InvalidFsPath,
 	InvalidArgument,
+	/// File or directory doesn't exist
+	NotFound,
+	/// File isn't a supported executable
+	InvalidExecutable,
+	/// Pointer to an invalid user-space address
+	BadAddress,
+	/// Arguments and environment don't fit on the initial stack
+	ArgumentListTooLong,
+	/// Path or file name is too long
+	NameTooLong,
+	/// File descriptor isn't open
+	BadFileDescriptor,
+	/// Too many open files
+	TooManyOpenFiles,
+	/// Operation isn't permitted
+	NotPermitted,
+	/// Not enough memory
+	OutOfMemory,
+	/// File or directory exists already
+	AlreadyExists,
+	/// A component of the path isn't a directory
+	NotADirectory,
+	/// Operation isn't supported by a directory
+	IsADirectory,
+	/// File doesn't support seeking
+	IllegalSeek,
+	/// Function isn't implemented
+	NotImplemented,
+	/// The operation didn't finish before its timeout
+	TimedOut,
+}
+
+/// Operation not permitted
+pub const EPERM: i32 = 1;
+/// No such file or directory
+pub const ENOENT: i32 = 2;
+/// Argument list too long
+pub const E2BIG: i32 = 7;
+/// Exec format error
+pub const ENOEXEC: i32 = 8;
+/// Bad file descriptor
+pub const EBADF: i32 = 9;
+/// Out of memory
+pub const ENOMEM: i32 = 12;
+/// Bad address
+pub const EFAULT: i32 = 14;
+/// File exists
+pub const EEXIST: i32 = 17;
+/// Not a directory
+pub const ENOTDIR: i32 = 20;
+/// Is a directory
+pub const EISDIR: i32 = 21;
+/// Invalid argument
+pub const EINVAL: i32 = 22;
+/// Too many open files
+pub const EMFILE: i32 = 24;
+/// Illegal seek
+pub const ESPIPE: i32 = 29;
+/// File name too long
+pub const ENAMETOOLONG: i32 = 36;
+/// Function not implemented
+pub const ENOSYS: i32 = 38;
+/// Connection timed out
+pub const ETIMEDOUT: i32 = 110;
+
+impl Error {
+	/// Returns the corresponding POSIX error number
+	pub fn errno(&self) -> i32 {
+		match *self {
+			Error::BadPriority => EINVAL,
+			Error::BadFsKind => EINVAL,
+			Error::BadFsOperation => EINVAL,
+			// the file isn't opened for reading or writing
+			Error::BadFsPermission => EBADF,
+			Error::InvalidFsPath => EINVAL,
+			Error::InvalidArgument => EINVAL,
+			Error::NotFound => ENOENT,
+			Error::InvalidExecutable => ENOEXEC,
+			Error::BadAddress => EFAULT,
+			Error::ArgumentListTooLong => E2BIG,
+			Error::NameTooLong => ENAMETOOLONG,
+			Error::BadFileDescriptor => EBADF,
+			Error::TooManyOpenFiles => EMFILE,
+			Error::NotPermitted => EPERM,
+			Error::OutOfMemory => ENOMEM,
+			Error::AlreadyExists => EEXIST,
+			Error::NotADirectory => ENOTDIR,
+			Error::IsADirectory => EISDIR,
+			Error::IllegalSeek => ESPIPE,
+			Error::NotImplemented => ENOSYS,
+			Error::TimedOut => ETIMEDOUT
+		}
+	}
 }
 
 impl fmt::Display for Error {
@@ -31,7 +124,22 @@ impl fmt::Display for Error {
 			Error::BadFsOperation => write!(f, "Bad file system operation"),
 			Error::BadFsPermission => write!(f, "Bad file permission"),
 			Error::InvalidFsPath => write!(f, "Invalid file system path"),
-			Error::InvalidArgument => write!(f, "Inavlid argument")
+			Error::InvalidArgument => write!(f, "Inavlid argument"),
+			Error::NotFound => write!(f, "No such file or directory"),
+			Error::InvalidExecutable => write!(f, "Invalid executable"),
+			Error::BadAddress => write!(f, "Bad address"),
+			Error::ArgumentListTooLong => write!(f, "Argument list too long"),
+			Error::NameTooLong => write!(f, "File name too long"),
+			Error::BadFileDescriptor => write!(f, "Bad file descriptor"),
+			Error::TooManyOpenFiles => write!(f, "Too many open files"),
+			Error::NotPermitted => write!(f, "Operation not permitted"),
+			Error::OutOfMemory => write!(f, "Out of memory"),
+			Error::AlreadyExists => write!(f, "File exists"),
+			Error::NotADirectory => write!(f, "Not a directory"),
+			Error::IsADirectory => write!(f, "Is a directory"),
+			Error::IllegalSeek => write!(f, "Illegal seek"),
+			Error::NotImplemented => write!(f, "Function not implemented"),
+			Error::TimedOut => write!(f, "Timed out")
 		}
 	}
 }
@@ -0,0 +1,101 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Table of the open files of a task
+//!
+//! A file descriptor is an index in the table. Forked tasks inherit a copy
+//! of the table and share the file handles (and consequently the file
+//! positions) with their parent. A file handle is protected by a mutex,
+//! because the tasks, which share it, may be preempted during a system call.
+
+use errno::*;
+use fs::FileHandle;
+use fs::stdio::{Stdin,Stdout};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use synch::mutex::Mutex;
+use consts::*;
+
+/// Standard input
+pub const STDIN_FILENO: i32 = 0;
+/// Standard output
+pub const STDOUT_FILENO: i32 = 1;
+/// Standard error
+pub const STDERR_FILENO: i32 = 2;
+
+/// Handle of an open file, which may be referenced by several descriptors
+pub type FileRef = Rc<Mutex<Box<FileHandle>>>;
+
+#[derive(Clone)]
+pub struct FileDescriptorTable {
+	files: Vec<Option<FileRef>>
+}
+
+impl FileDescriptorTable {
+	/// Create a table, which contains only the standard input and output
+	pub fn new() -> Self {
+		let stdout: FileRef = Rc::new(Mutex::new(Box::new(Stdout)));
+		let mut files: Vec<Option<FileRef>> = Vec::with_capacity(3);
+
+		files.push(Some(Rc::new(Mutex::new(Box::new(Stdin)))));
+		files.push(Some(stdout.clone()));
+		files.push(Some(stdout));
+
+		FileDescriptorTable {
+			files: files
+		}
+	}
+
+	/// Add `file` to the table and return the lowest unused file descriptor
+	pub fn insert(&mut self, file: Box<FileHandle>) -> Result<i32> {
+		let file = Some(Rc::new(Mutex::new(file)));
+
+		match self.files.iter().position(|f| f.is_none()) {
+			Some(fd) => {
+				self.files[fd] = file;
+				Ok(fd as i32)
+			},
+			None => {
+				if self.files.len() >= MAX_FILE_DESCRIPTORS {
+					return Err(Error::TooManyOpenFiles);
+				}
+
+				self.files.push(file);
+				Ok(self.files.len() as i32 - 1)
+			}
+		}
+	}
+
+	/// Returns the file, which is referenced by `fd`
+	pub fn get(&self, fd: i32) -> Result<FileRef> {
+		if fd < 0 {
+			return Err(Error::BadFileDescriptor);
+		}
+
+		match self.files.get(fd as usize) {
+			Some(&Some(ref file)) => Ok(file.clone()),
+			_ => Err(Error::BadFileDescriptor)
+		}
+	}
+
+	/// Remove `fd` from the table. The file is closed, if
+	/// it isn't referenced by another descriptor.
+	pub fn remove(&mut self, fd: i32) -> Result<()> {
+		if fd < 0 {
+			return Err(Error::BadFileDescriptor);
+		}
+
+		match self.files.get_mut(fd as usize) {
+			Some(file) if file.is_some() => {
+				*file = None;
+				Ok(())
+			},
+			_ => Err(Error::BadFileDescriptor)
+		}
+	}
+}
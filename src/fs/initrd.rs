@@ -40,9 +40,16 @@ impl RomHandle {
 	}
 
 	pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-		let vec = self.data.read();
 		let mut pos_guard = self.pos.lock();
-		let pos = *pos_guard;
+		let len = self.read_at(buf, *pos_guard)?;
+
+		*pos_guard += len;
+
+		Ok(len)
+	}
+
+	pub fn read_at(&self, buf: &mut [u8], pos: usize) -> Result<usize> {
+		let vec = self.data.read();
 
 		if pos >= vec.len() {
 			return Ok(0)
@@ -56,11 +63,21 @@ impl RomHandle {
 		}
 
 		buf[0..len].clone_from_slice(&vec[pos..pos + len]);
-		*pos_guard = pos + len;
 
 		Ok(len)
 	}
 
+	/// Returns the address of the file content at `pos`
+	pub fn address(&self, pos: usize) -> Option<usize> {
+		let guard = self.data.read();
+
+		if pos < guard.len() {
+			Some(guard.as_ptr() as usize + pos)
+		} else {
+			None
+		}
+	}
+
 	pub fn seek(&mut self, style: SeekFrom) -> Result<u64> {
 		let mut pos_guard = self.pos.lock();
 
@@ -112,8 +129,9 @@ pub struct RamHandle {
 	writeable: bool,
 	/// Position within the file
 	pos: Spinlock<usize>,
-	/// File content
-	data: Arc<RwLock<Vec<u8>>>
+	/// File content. The page-fault handler reads the content with disabled
+	/// interrupts, so the lock must not be held by a preempted task.
+	data: Arc<SpinlockIrqSave<Vec<u8>>>
 }
 
 impl RamHandle {
@@ -121,15 +139,22 @@ impl RamHandle {
 		RamHandle {
 			writeable: writeable,
 			pos: Spinlock::new(0),
-			data: Arc::new(RwLock::new(Vec::new()))
+			data: Arc::new(SpinlockIrqSave::new(Vec::new()))
 		}
 	}
 
 	pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-		let guard = self.data.read();
-		let ref vec: &Vec<u8> = guard.deref();
 		let mut pos_guard = self.pos.lock();
-		let pos = *pos_guard;
+		let len = self.read_at(buf, *pos_guard)?;
+
+		*pos_guard += len;
+
+		Ok(len)
+	}
+
+	pub fn read_at(&self, buf: &mut [u8], pos: usize) -> Result<usize> {
+		let guard = self.data.lock();
+		let ref vec: &Vec<u8> = guard.deref();
 
 		if pos >= vec.len() {
 			return Ok(0)
@@ -143,7 +168,6 @@ impl RamHandle {
 		}
 
 		buf[0..len].clone_from_slice(&vec[pos..pos + len]);
-		*pos_guard = pos + len;
 
 		Ok(len)
 	}
@@ -153,7 +177,7 @@ impl RamHandle {
 			return Err(Error::BadFsPermission);
 		}
 
-		let mut guard = self.data.write();
+		let mut guard = self.data.lock();
 		let ref mut vec: &mut Vec<u8> = guard.deref_mut();
 		let mut pos_guard = self.pos.lock();
 		let pos = *pos_guard;
@@ -177,7 +201,7 @@ impl RamHandle {
 				Ok(n)
 			}
 			SeekFrom::End(n) => {
-				let guard = self.data.read();
+				let guard = self.data.lock();
 				let ref vec: &Vec<u8> = guard.deref();
 				let data = vec.len() as i64 + n;
 				if data >= 0 {
@@ -204,7 +228,7 @@ impl RamHandle {
 			return Err(core::fmt::Error);
 		}
 
-		let mut guard = self.data.write();
+		let mut guard = self.data.lock();
 		let ref mut vec: &mut Vec<u8> = guard.deref_mut();
 		let mut pos_guard = self.pos.lock();
 		let pos = *pos_guard;
@@ -228,7 +252,7 @@ impl RamHandle {
 	}
 
 	pub fn len(&self) -> usize {
-		let guard = self.data.read();
+		let guard = self.data.lock();
 		let ref vec: &Vec<u8> = guard.deref();
 		vec.len() as usize
 	}
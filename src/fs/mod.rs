@@ -11,6 +11,8 @@
 
 mod vfs;
 mod initrd;
+mod fd;
+mod stdio;
 
 use logging::*;
 use errno::*;
@@ -19,6 +21,7 @@ use fs::vfs::Fs;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 use alloc::string::String;
+pub use fs::fd::{FileDescriptorTable,FileRef,STDIN_FILENO,STDOUT_FILENO,STDERR_FILENO};
 
 /// Type of the VfsNode
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -114,6 +117,13 @@ pub trait FileHandle: core::fmt::Debug + core::fmt::Write {
 	fn write(&mut self, buf: &[u8]) -> Result<usize>;
 	fn seek(&mut self, style: SeekFrom) -> Result<u64>;
 	fn len(&self) -> usize;
+	/// Read from the position `offset` without changing the file position
+	fn read_at(&self, buf: &mut [u8], offset: usize) -> Result<usize>;
+	/// Returns the address of the content at `offset`, if the file is located
+	/// in identity-mapped read-only memory, which can be mapped directly
+	fn rom_address(&self, offset: usize) -> Option<usize>;
+	/// Create a handle of the same file with its own file position
+	fn duplicate(&self) -> Result<Box<FileHandle>>;
 }
 
 /// Entrypoint of the file system
@@ -0,0 +1,97 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Standard input and output of a task, which are connected to the console
+
+use errno::*;
+use fs::{FileHandle, SeekFrom};
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+
+/// Standard input, the console doesn't support input
+#[derive(Debug)]
+pub struct Stdin;
+
+impl fmt::Write for Stdin {
+	fn write_str(&mut self, _s: &str) -> fmt::Result {
+		Err(fmt::Error)
+	}
+}
+
+impl FileHandle for Stdin {
+	/// Returns always the end of file
+	fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+		Ok(0)
+	}
+
+	fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+		Err(Error::BadFsPermission)
+	}
+
+	fn seek(&mut self, _style: SeekFrom) -> Result<u64> {
+		Err(Error::IllegalSeek)
+	}
+
+	fn len(&self) -> usize {
+		0
+	}
+
+	fn read_at(&self, _buf: &mut [u8], _offset: usize) -> Result<usize> {
+		Err(Error::IllegalSeek)
+	}
+
+	fn rom_address(&self, _offset: usize) -> Option<usize> {
+		None
+	}
+
+	fn duplicate(&self) -> Result<Box<FileHandle>> {
+		Ok(Box::new(Stdin))
+	}
+}
+
+/// Standard output and standard error, which are printed on the console
+#[derive(Debug)]
+pub struct Stdout;
+
+impl fmt::Write for Stdout {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		print!("{}", s);
+		Ok(())
+	}
+}
+
+impl FileHandle for Stdout {
+	fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+		Err(Error::BadFsPermission)
+	}
+
+	fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		print!("{}", String::from_utf8_lossy(buf));
+		Ok(buf.len())
+	}
+
+	fn seek(&mut self, _style: SeekFrom) -> Result<u64> {
+		Err(Error::IllegalSeek)
+	}
+
+	fn len(&self) -> usize {
+		0
+	}
+
+	fn read_at(&self, _buf: &mut [u8], _offset: usize) -> Result<usize> {
+		Err(Error::IllegalSeek)
+	}
+
+	fn rom_address(&self, _offset: usize) -> Option<usize> {
+		None
+	}
+
+	fn duplicate(&self) -> Result<Box<FileHandle>> {
+		Ok(Box::new(Stdout))
+	}
+}
@@ -66,6 +66,10 @@ impl VfsNodeDirectory for VfsDirectory {
 				}
 			}
 
+			if self.children.contains_key(&node_name) {
+				return Err(Error::AlreadyExists);
+			}
+
 			let mut directory = Box::new(VfsDirectory::new());
 			let result = directory.traverse_mkdir(components);
 			self.children.insert(node_name, directory);
@@ -101,6 +105,10 @@ impl VfsNodeDirectory for VfsDirectory {
 				if let Some(file) = self.get_mut::<VfsFile>(&node_name) {
 					return file.get_handle(flags);
 				}
+
+				if self.get::<VfsDirectory>(&node_name).is_some() {
+					return Err(Error::IsADirectory);
+				}
 			}
 
 			if components.is_empty() == true {
@@ -112,14 +120,16 @@ impl VfsNodeDirectory for VfsDirectory {
 
 					result
 				} else {
-					Err(Error::InvalidArgument)
+					Err(Error::NotFound)
 				}
 			} else {
 				// traverse to the directories to the endpoint
 				if let Some(directory) = self.get_mut::<VfsDirectory>(&node_name) {
 					directory.traverse_open(components, flags)
+				} else if self.children.contains_key(&node_name) {
+					Err(Error::NotADirectory)
 				} else {
-					Err(Error::InvalidArgument)
+					Err(Error::NotFound)
 				}
 			}
 		} else {
@@ -239,6 +249,24 @@ impl FileHandle for VfsFile {
 			DataHandle::ROM(ref data) => { data.len() }
 		}
 	}
+
+	fn read_at(&self, buf: &mut [u8], offset: usize) -> Result<usize> {
+		match self.data {
+			DataHandle::RAM(ref data) => { data.read_at(buf, offset) },
+			DataHandle::ROM(ref data) => { data.read_at(buf, offset) }
+		}
+	}
+
+	fn rom_address(&self, offset: usize) -> Option<usize> {
+		match self.data {
+			DataHandle::ROM(ref data) => { data.address(offset) },
+			_ => None
+		}
+	}
+
+	fn duplicate(&self) -> Result<Box<FileHandle>> {
+		Ok(Box::new(self.clone()))
+	}
 }
 
 /// Entrypoint of the in-memory file system
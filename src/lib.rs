@@ -1,4 +1,4 @@
-#![feature(asm, const_fn, lang_items)]
+#![feature(asm, const_fn, lang_items, global_asm)]
 #![feature(allocator_api)]
 #![feature(panic_info_message)]
 #![feature(compiler_builtins_lib)]
@@ -36,6 +36,7 @@ pub mod errno;
 pub mod synch;
 pub mod syscall;
 pub mod fs;
+pub mod time;
 
 #[global_allocator]
 static ALLOCATOR: &'static mm::allocator::Allocator = &mm::allocator::Allocator;
@@ -53,13 +53,19 @@ pub extern "C" fn main() -> ! {
 	for _i in 0..2 {
 		scheduler::spawn(foo, NORMAL_PRIORITY).unwrap();
 	}
-	scheduler::spawn(create_user_foo, NORMAL_PRIORITY).unwrap();
+	for _i in 0..2 {
+		scheduler::spawn(create_user_foo, NORMAL_PRIORITY).unwrap();
+	}
 
 	// enable interrupts => enable preemptive multitasking
 	arch::irq::irq_enable();
 
 	scheduler::reschedule();
 
+	scheduler::check_stacks();
+	mm::allocator::print_statistics();
+	arch::mm::physicalmem::print_statistics();
+
 	println!("Shutdown system!");
 
 	// shutdown system
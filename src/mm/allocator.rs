@@ -13,6 +13,9 @@
 //! "Bootstrap Allocator". This is a simple single-threaded implementation using some
 //! preallocated space, along with an index variable.
 //! Freed memory is never reused, but this can be neglected for bootstrapping.
+//!
+//! Afterwards, small objects are served by a size-class allocator, while larger
+//! objects are directly mapped by `mm::allocate`.
 
 use alloc::alloc::Layout;
 use core::alloc::GlobalAlloc;
@@ -20,6 +23,8 @@ use arch::{PageSize,BasePageSize};
 use consts::*;
 use logging::*;
 use mm;
+use mm::slab::SlabAllocator;
+use scheduler::DisabledPreemption;
 
 /// Size of the preallocated space for the Bootstrap Allocator.
 const BOOTSTRAP_HEAP_SIZE: usize = 0x1000;
@@ -55,6 +60,9 @@ impl AllocatorInfo {
 
 static mut ALLOCATOR_INFO: AllocatorInfo = AllocatorInfo::new();
 
+/// Allocator for objects, which are smaller than a page
+static mut SLAB_ALLOCATOR: SlabAllocator = SlabAllocator::new();
+
 pub struct Allocator;
 
 unsafe impl<'a> GlobalAlloc for &'a Allocator {
@@ -99,6 +107,13 @@ unsafe fn alloc_bootstrap(layout: Layout) -> *mut u8 {
 fn alloc_system(layout: Layout) -> *mut u8 {
 	debug!("Allocating {} bytes using the System Allocator", layout.size());
 
+	{
+		let _preemption = DisabledPreemption::new();
+		if let Some(ptr) = unsafe { SLAB_ALLOCATOR.allocate(&layout) } {
+			return ptr;
+		}
+	}
+
 	let size = align_up!(layout.size(), BasePageSize::SIZE);
 	mm::allocate(size, true) as *mut u8
 }
@@ -107,10 +122,23 @@ fn alloc_system(layout: Layout) -> *mut u8 {
 fn dealloc_system(virtual_address: usize, layout: Layout) {
 	debug!("Deallocating {} bytes at {:#X} using the System Allocator", layout.size(), virtual_address);
 
+	{
+		let _preemption = DisabledPreemption::new();
+		if unsafe { SLAB_ALLOCATOR.deallocate(virtual_address, &layout) } {
+			return;
+		}
+	}
+
 	let size = align_up!(layout.size(), BasePageSize::SIZE);
 	mm::deallocate(virtual_address, size);
 }
 
+/// Print the usage of the kernel heap
+pub fn print_statistics() {
+	let _preemption = DisabledPreemption::new();
+	unsafe { SLAB_ALLOCATOR.print_statistics(); }
+}
+
 pub fn init() {
 	unsafe { ALLOCATOR_INFO.switch_to_system_allocator(); }
 }
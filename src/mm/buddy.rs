@@ -0,0 +1,318 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Implementation of a buddy allocator for page frames.
+//!
+//! Free blocks of 2^order frames are kept in one list per order. The management
+//! information is stored in a table with one descriptor per frame, because free
+//! frames are not mapped into the kernel space. Only the first frame of a free
+//! block is linked into a list. Allocation and deallocation split and merge
+//! blocks and need O(log n) steps.
+//!
+//! In addition, the descriptor counts the additional references to an
+//! allocated frame, which is shared by several address spaces (copy-on-write).
+
+use core::{cmp, ptr};
+use core::mem::size_of;
+use arch::{PageSize,BasePageSize};
+use logging::*;
+
+/// Number of supported orders. The largest block has a size
+/// of 2^18 frames (1 GiB).
+pub const NO_ORDERS: usize = 19;
+
+/// Marks the end of a list
+const INVALID_FRAME: u32 = u32::max_value();
+
+/// Descriptor of a page frame
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct FrameDescriptor {
+	/// Index of the next free block with the same order
+	next: u32,
+	/// Index of the previous free block with the same order
+	prev: u32,
+	/// Order of the free block, which starts with this frame
+	order: u8,
+	/// Is this frame the first frame of a free block?
+	free: bool,
+	/// Number of additional references to this frame
+	shared: u16
+}
+
+pub struct BuddyAllocator {
+	/// Table of frame descriptors
+	frames: *mut FrameDescriptor,
+	/// Page frame number of the first frame in the table
+	base: usize,
+	/// Number of frames in the table
+	count: usize,
+	/// Index of the first free block for each order
+	free_lists: [u32; NO_ORDERS],
+	/// Number of free frames
+	free_frames: usize
+}
+
+impl BuddyAllocator {
+	pub const fn new() -> Self {
+		BuddyAllocator {
+			frames: ptr::null_mut(),
+			base: 0,
+			count: 0,
+			free_lists: [INVALID_FRAME; NO_ORDERS],
+			free_frames: 0
+		}
+	}
+
+	/// Returns the size of the descriptor table to manage
+	/// all frames between `start` and `end`.
+	pub fn table_size(start: usize, end: usize) -> usize {
+		(end - start) / BasePageSize::SIZE * size_of::<FrameDescriptor>()
+	}
+
+	/// Initialize the allocator to manage the frames between `start` and `end`.
+	/// `table` must point to a mapped memory region of `table_size(start, end)` bytes.
+	/// Initially, all frames are marked as used.
+	pub unsafe fn init(&mut self, table: usize, start: usize, end: usize) {
+		self.frames = table as *mut FrameDescriptor;
+		self.base = start / BasePageSize::SIZE;
+		self.count = (end - start) / BasePageSize::SIZE;
+
+		for i in 0..self.count {
+			*self.frames.offset(i as isize) = FrameDescriptor {
+				next: INVALID_FRAME,
+				prev: INVALID_FRAME,
+				order: 0,
+				free: false,
+				shared: 0
+			};
+		}
+	}
+
+	#[inline]
+	fn frame(&self, index: u32) -> &mut FrameDescriptor {
+		assert!((index as usize) < self.count, "Frame index {} is out of range", index);
+		unsafe { &mut *self.frames.offset(index as isize) }
+	}
+
+	/// Converts a physical address to an index in the descriptor table
+	#[inline]
+	fn index(&self, address: usize) -> u32 {
+		(address / BasePageSize::SIZE - self.base) as u32
+	}
+
+	/// Converts an index in the descriptor table to a physical address
+	#[inline]
+	fn address(&self, index: u32) -> usize {
+		(self.base + index as usize) * BasePageSize::SIZE
+	}
+
+	fn push(&mut self, index: u32, order: usize) {
+		let head = self.free_lists[order];
+
+		{
+			let frame = self.frame(index);
+			frame.next = head;
+			frame.prev = INVALID_FRAME;
+			frame.order = order as u8;
+			frame.free = true;
+		}
+
+		if head != INVALID_FRAME {
+			self.frame(head).prev = index;
+		}
+		self.free_lists[order] = index;
+	}
+
+	fn remove(&mut self, index: u32, order: usize) {
+		let (prev, next) = {
+			let frame = self.frame(index);
+			frame.free = false;
+			(frame.prev, frame.next)
+		};
+
+		if prev != INVALID_FRAME {
+			self.frame(prev).next = next;
+		} else {
+			self.free_lists[order] = next;
+		}
+
+		if next != INVALID_FRAME {
+			self.frame(next).prev = prev;
+		}
+	}
+
+	/// Checks if the block with the first frame `index` is free and has the given order
+	fn is_free_block(&self, index: u32, order: usize) -> bool {
+		if index as usize >= self.count {
+			return false;
+		}
+
+		let frame = self.frame(index);
+		frame.free && frame.order as usize == order
+	}
+
+	/// Release a block and merge it with its buddies
+	fn free_block(&mut self, mut index: u32, mut order: usize) {
+		while order < NO_ORDERS - 1 {
+			// buddies are determined by the page frame number to
+			// guarantee that a block is aligned to its size
+			let pfn = self.base + index as usize;
+			let buddy_pfn = pfn ^ (1 << order);
+
+			if buddy_pfn < self.base {
+				break;
+			}
+
+			let buddy = (buddy_pfn - self.base) as u32;
+			if !self.is_free_block(buddy, order) {
+				break;
+			}
+
+			self.remove(buddy, order);
+			index = cmp::min(index, buddy);
+			order += 1;
+		}
+
+		self.push(index, order);
+	}
+
+	/// Release all frames in [start, end) by splitting the range into
+	/// the largest possible aligned blocks.
+	fn free_range(&mut self, start: usize, end: usize) {
+		let mut pfn = start / BasePageSize::SIZE;
+		let end_pfn = end / BasePageSize::SIZE;
+
+		while pfn < end_pfn {
+			let mut order = 0;
+			while order < NO_ORDERS - 1
+				&& pfn % (1 << (order + 1)) == 0
+				&& pfn + (1 << (order + 1)) <= end_pfn {
+				order += 1;
+			}
+
+			let index = (pfn - self.base) as u32;
+			self.free_block(index, order);
+			pfn += 1 << order;
+		}
+	}
+
+	/// Returns the smallest order, whose blocks include `size` bytes
+	fn order(size: usize) -> usize {
+		let frames = size / BasePageSize::SIZE;
+		frames.next_power_of_two().trailing_zeros() as usize
+	}
+
+	/// Add the free memory region [start, end) to the allocator
+	pub fn add_region(&mut self, start: usize, end: usize) {
+		assert!(start / BasePageSize::SIZE >= self.base && end / BasePageSize::SIZE <= self.base + self.count,
+			"Region 0x{:x} - 0x{:x} is not covered by the frame table", start, end);
+
+		self.free_range(start, end);
+		self.free_frames += (end - start) / BasePageSize::SIZE;
+	}
+
+	/// Allocate `size` bytes, which are aligned to `alignment`.
+	/// Both values must be a multiple of the page size.
+	pub fn allocate(&mut self, size: usize, alignment: usize) -> Result<usize, ()> {
+		let order = cmp::max(Self::order(size), Self::order(alignment));
+
+		if order >= NO_ORDERS {
+			return Err(());
+		}
+
+		// search the smallest free block, which is large enough
+		let mut current = order;
+		while current < NO_ORDERS && self.free_lists[current] == INVALID_FRAME {
+			current += 1;
+		}
+
+		if current == NO_ORDERS {
+			return Err(());
+		}
+
+		let index = self.free_lists[current];
+		self.remove(index, current);
+
+		// split the block and release the upper halves
+		while current > order {
+			current -= 1;
+			self.push(index + (1 << current), current);
+		}
+
+		// release the frames behind the requested size
+		let address = self.address(index);
+		let block_end = address + (BasePageSize::SIZE << order);
+		if address + size < block_end {
+			self.free_range(address + size, block_end);
+		}
+
+		self.free_frames -= size / BasePageSize::SIZE;
+
+		Ok(address)
+	}
+
+	/// Release `size` bytes at the physical address `address`
+	pub fn deallocate(&mut self, address: usize, size: usize) {
+		let index = self.index(address);
+		assert!((index as usize) < self.count, "Physical address {:#X} isn't managed by the buddy allocator", address);
+
+		self.free_range(address, address + size);
+		self.free_frames += size / BasePageSize::SIZE;
+	}
+
+	/// Add a reference to the allocated frame at `address`
+	pub fn share(&mut self, address: usize) {
+		let index = self.index(address);
+		let frame = self.frame(index);
+
+		assert!(!frame.free, "Unable to share the free frame {:#X}", address);
+		frame.shared = frame.shared.checked_add(1).expect("Too many references to a frame");
+	}
+
+	/// Remove a reference to the frame at `address` and release the frame,
+	/// if it isn't shared anymore. Returns true, if the frame is released.
+	pub fn release(&mut self, address: usize) -> bool {
+		let index = self.index(address);
+
+		{
+			let frame = self.frame(index);
+			if frame.shared > 0 {
+				frame.shared -= 1;
+				return false;
+			}
+		}
+
+		self.deallocate(address, BasePageSize::SIZE);
+		true
+	}
+
+	/// Returns true, if the frame at `address` is referenced by several address spaces
+	pub fn is_shared(&self, address: usize) -> bool {
+		self.frame(self.index(address)).shared > 0
+	}
+
+	/// Print the number of free blocks for each order
+	pub fn print_statistics(&self) {
+		info!("Free physical memory: {} KByte", (self.free_frames * BasePageSize::SIZE) >> 10);
+
+		for order in 0..NO_ORDERS {
+			let mut blocks = 0;
+			let mut index = self.free_lists[order];
+
+			while index != INVALID_FRAME {
+				blocks += 1;
+				index = self.frame(index).next;
+			}
+
+			if blocks > 0 {
+				info!("Order {:2} ({:7} KByte): {} free blocks", order,
+					(BasePageSize::SIZE << order) >> 10, blocks);
+			}
+		}
+	}
+}
@@ -6,8 +6,12 @@
 // copied, modified, or distributed except according to those terms.
 
 pub mod allocator;
+pub mod buddy;
 pub mod freelist;
+pub mod user;
+pub mod vma;
 mod nodepool;
+mod slab;
 
 use alloc::alloc::Layout;
 use arch::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
@@ -67,6 +71,39 @@ pub fn deallocate(virtual_address: usize, size: usize) {
 	}
 }
 
+/// Allocate `size` bytes of writable memory, which is protected by an unmapped
+/// guard page below it. An overflow into the guard page triggers a page fault.
+pub fn allocate_guarded(size: usize) -> usize {
+	let _preemption = DisabledPreemption::new();
+
+	let physical_address = arch::mm::physicalmem::allocate(size);
+	let guard_address = arch::mm::virtualmem::allocate(size + BasePageSize::SIZE);
+	let virtual_address = guard_address + BasePageSize::SIZE;
+
+	let count = size / BasePageSize::SIZE;
+	let mut flags = PageTableEntryFlags::empty();
+	flags.normal().writable().execute_disable();
+
+	// the range may be used before => remove a stale mapping of the guard page
+	arch::mm::paging::unmap::<BasePageSize>(guard_address, 1);
+	arch::mm::paging::map::<BasePageSize>(virtual_address, physical_address, count, flags);
+
+	virtual_address
+}
+
+/// Release memory, which is allocated by `allocate_guarded`
+pub fn deallocate_guarded(virtual_address: usize, size: usize) {
+	let _preemption = DisabledPreemption::new();
+
+	if let Some(entry) = arch::mm::paging::get_page_table_entry::<BasePageSize>(virtual_address) {
+		arch::mm::paging::unmap::<BasePageSize>(virtual_address, size / BasePageSize::SIZE);
+		arch::mm::virtualmem::deallocate(virtual_address - BasePageSize::SIZE, size + BasePageSize::SIZE);
+		arch::mm::physicalmem::deallocate(entry.address(), size);
+	} else {
+		panic!("No page table entry for virtual address {:#X}", virtual_address);
+	}
+}
+
 pub fn init() {
 	let image_size;
 
@@ -0,0 +1,158 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Implementation of a size-class allocator for small kernel objects.
+//!
+//! Each size class manages a list of free objects. If the list is empty, the
+//! allocator takes a new page from `mm::allocate` and splits it into objects of
+//! the respective size. All sizes are powers of two and the pages are page-aligned.
+//! Consequently, each object is naturally aligned to its size.
+
+use alloc::alloc::Layout;
+use core::{cmp, ptr};
+use arch::{PageSize,BasePageSize};
+use logging::*;
+use mm;
+
+/// Number of supported size classes
+const NO_SIZE_CLASSES: usize = 8;
+
+/// Size of the smallest class
+const MIN_OBJECT_SIZE: usize = 16;
+
+/// Size of the largest class. Larger objects are directly allocated
+/// by `mm::allocate`.
+pub const MAX_OBJECT_SIZE: usize = MIN_OBJECT_SIZE << (NO_SIZE_CLASSES - 1);
+
+/// A free object stores the link to the next free object of the same size
+struct FreeObject {
+	next: *mut FreeObject
+}
+
+/// Management information of a size class
+struct SizeClass {
+	/// Size of each object in this class
+	size: usize,
+	/// List of free objects
+	free_list: *mut FreeObject,
+	/// Number of pages, which are used by this class
+	pages: usize,
+	/// Number of objects, which are currently in use
+	used: usize,
+	/// Number of objects in the free list
+	free: usize
+}
+
+impl SizeClass {
+	const fn new(size: usize) -> Self {
+		SizeClass {
+			size: size,
+			free_list: ptr::null_mut(),
+			pages: 0,
+			used: 0,
+			free: 0
+		}
+	}
+
+	/// Split a new page into objects and add them to the free list
+	fn refill(&mut self) {
+		let page = mm::allocate(BasePageSize::SIZE, true);
+
+		debug!("Refill size class {} with page 0x{:x}", self.size, page);
+
+		for i in (0..BasePageSize::SIZE / self.size).rev() {
+			let object = (page + i * self.size) as *mut FreeObject;
+
+			unsafe { (*object).next = self.free_list; }
+			self.free_list = object;
+			self.free += 1;
+		}
+
+		self.pages += 1;
+	}
+
+	fn allocate(&mut self) -> *mut u8 {
+		if self.free_list.is_null() {
+			self.refill();
+		}
+
+		let object = self.free_list;
+		self.free_list = unsafe { (*object).next };
+		self.free -= 1;
+		self.used += 1;
+
+		object as *mut u8
+	}
+
+	fn deallocate(&mut self, address: usize) {
+		let object = address as *mut FreeObject;
+
+		unsafe { (*object).next = self.free_list; }
+		self.free_list = object;
+		self.free += 1;
+		self.used -= 1;
+	}
+}
+
+pub struct SlabAllocator {
+	classes: [SizeClass; NO_SIZE_CLASSES]
+}
+
+impl SlabAllocator {
+	pub const fn new() -> Self {
+		SlabAllocator {
+			classes: [
+				SizeClass::new(MIN_OBJECT_SIZE),
+				SizeClass::new(MIN_OBJECT_SIZE << 1),
+				SizeClass::new(MIN_OBJECT_SIZE << 2),
+				SizeClass::new(MIN_OBJECT_SIZE << 3),
+				SizeClass::new(MIN_OBJECT_SIZE << 4),
+				SizeClass::new(MIN_OBJECT_SIZE << 5),
+				SizeClass::new(MIN_OBJECT_SIZE << 6),
+				SizeClass::new(MIN_OBJECT_SIZE << 7)
+			]
+		}
+	}
+
+	/// Determines the size class, which satisfies the size and the alignment
+	/// of `layout`. Returns None, if the object is too large for a size class.
+	fn class_index(layout: &Layout) -> Option<usize> {
+		let size = cmp::max(cmp::max(layout.size(), layout.align()), MIN_OBJECT_SIZE);
+
+		if size > MAX_OBJECT_SIZE {
+			return None;
+		}
+
+		Some((size.next_power_of_two() / MIN_OBJECT_SIZE).trailing_zeros() as usize)
+	}
+
+	/// Allocate an object, which is described by `layout`. Returns None,
+	/// if the object is too large for a size class.
+	pub fn allocate(&mut self, layout: &Layout) -> Option<*mut u8> {
+		Self::class_index(layout).map(|i| self.classes[i].allocate())
+	}
+
+	/// Release an object, which was allocated by `allocate`. Returns false,
+	/// if the object doesn't belong to a size class.
+	pub fn deallocate(&mut self, address: usize, layout: &Layout) -> bool {
+		match Self::class_index(layout) {
+			Some(i) => {
+				self.classes[i].deallocate(address);
+				true
+			},
+			None => false
+		}
+	}
+
+	/// Print the usage of each size class
+	pub fn print_statistics(&self) {
+		for class in self.classes.iter() {
+			info!("Size class {:4} bytes: {:3} pages, {:5} objects in use, {:5} free objects",
+				class.size, class.pages, class.used, class.free);
+		}
+	}
+}
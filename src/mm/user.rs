@@ -0,0 +1,134 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Helper functions to access user-space buffers in system calls
+//!
+//! The kernel never dereferences a pointer of the user space directly.
+//! Invalid pointers are reported as `Error::BadAddress`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{cmp,mem,slice};
+use arch::mm::paging::{BasePageSize,PageSize};
+use errno::*;
+pub use arch::mm::uaccess::{copy_from_user,copy_to_user,is_user_range};
+
+/// A buffer in the user space
+#[derive(Copy, Clone, Debug)]
+pub struct UserSlice {
+	addr: usize,
+	len: usize
+}
+
+impl UserSlice {
+	/// Describe the user-space buffer [addr, addr + len)
+	pub fn new(addr: usize, len: usize) -> Result<Self> {
+		if len > 0 && !is_user_range(addr, len) {
+			return Err(Error::BadAddress);
+		}
+
+		Ok(UserSlice {
+			addr: addr,
+			len: len
+		})
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns the part of the buffer, which starts at `offset` and has at most `len` bytes
+	pub fn subslice(&self, offset: usize, len: usize) -> UserSlice {
+		let offset = cmp::min(offset, self.len);
+
+		UserSlice {
+			addr: self.addr + offset,
+			len: cmp::min(len, self.len - offset)
+		}
+	}
+
+	/// Copy the first `buf.len()` bytes of the buffer into the kernel
+	pub fn read(&self, buf: &mut [u8]) -> Result<()> {
+		if buf.len() > self.len {
+			return Err(Error::InvalidArgument);
+		}
+
+		copy_from_user(buf, self.addr)
+	}
+
+	/// Copy `data` to the begin of the buffer
+	pub fn write(&self, data: &[u8]) -> Result<()> {
+		if data.len() > self.len {
+			return Err(Error::InvalidArgument);
+		}
+
+		copy_to_user(self.addr, data)
+	}
+
+	/// Copy the whole buffer into the kernel
+	pub fn read_to_vec(&self) -> Result<Vec<u8>> {
+		let mut buf: Vec<u8> = Vec::new();
+
+		buf.resize(self.len, 0);
+		self.read(&mut buf)?;
+
+		Ok(buf)
+	}
+}
+
+/// Read a value of type `T` from the user-space address `addr`
+pub fn get_user<T: Copy>(addr: usize) -> Result<T> {
+	unsafe {
+		let mut value: T = mem::zeroed();
+		let buf = slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, mem::size_of::<T>());
+
+		copy_from_user(buf, addr)?;
+
+		Ok(value)
+	}
+}
+
+/// Write `value` to the user-space address `addr`
+pub fn put_user<T: Copy>(addr: usize, value: T) -> Result<()> {
+	let buf = unsafe { slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+
+	copy_to_user(addr, buf)
+}
+
+/// Copy a null-terminated string with at most `max_len` bytes from the user space.
+/// A longer string is rejected with `too_long`.
+pub fn copy_string_from_user(addr: usize, max_len: usize, too_long: Error) -> Result<String> {
+	let mut bytes: Vec<u8> = Vec::new();
+	let mut current = addr;
+
+	if addr == 0 {
+		return Err(Error::BadAddress);
+	}
+
+	loop {
+		// copy up to the end of the page to avoid faults behind the string
+		let mut chunk = [0u8; 256];
+		let len = cmp::min(chunk.len(), align_up!(current + 1, BasePageSize::SIZE) - current);
+
+		copy_from_user(&mut chunk[..len], current)?;
+
+		let end = chunk[..len].iter().position(|b| *b == 0);
+		bytes.extend_from_slice(&chunk[..end.unwrap_or(len)]);
+
+		if bytes.len() > max_len {
+			return Err(too_long);
+		}
+
+		if end.is_some() {
+			break;
+		}
+
+		current += len;
+	}
+
+	Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,369 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Virtual memory areas (VMAs) of a user-level process
+//!
+//! A VMA describes a page-aligned region of the user space and the allowed
+//! accesses. The page fault handler maps pages only inside of a VMA, every
+//! other access to the user space is a segmentation fault. Forked tasks
+//! inherit a copy of the table.
+//!
+//! An area may be backed by a file. Its pages are read from the file on the
+//! first access and changes are private to the process.
+//!
+//! The user stack is an area, which grows down on demand. It is bounded by
+//! the stack limit and always keeps an unmapped guard page to the area below.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use arch::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
+use consts::*;
+use errno::*;
+use fs::FileHandle;
+use logging::*;
+
+bitflags! {
+	/// Allowed accesses to a virtual memory area, the values
+	/// are identical to the protection flags of `mmap`
+	pub struct VmaFlags: u32 {
+		const READ = 1 << 0;
+		const WRITE = 1 << 1;
+		const EXECUTE = 1 << 2;
+		/// The area grows down on a page fault below it
+		const GROWS_DOWN = 1 << 24;
+		/// The area maps pages of the kernel (e.g. the vDSO) and can't be resized
+		const SPECIAL = 1 << 31;
+	}
+}
+
+impl VmaFlags {
+	/// Derive the flags of the page table entries. Pages without any
+	/// access (PROT_NONE) aren't mapped by the page fault handler, but
+	/// pages, which already exist, stay readable.
+	pub fn page_flags(&self) -> PageTableEntryFlags {
+		let mut flags = PageTableEntryFlags::USER_ACCESSIBLE;
+
+		if self.contains(VmaFlags::WRITE) {
+			flags.insert(PageTableEntryFlags::WRITABLE);
+		}
+		if !self.contains(VmaFlags::EXECUTE) {
+			flags.insert(PageTableEntryFlags::EXECUTE_DISABLE);
+		}
+
+		flags
+	}
+}
+
+/// File, which contains the initial content of an area
+#[derive(Clone, Debug)]
+pub struct VmaFile {
+	/// Handle of the file, which is only used by the memory mapping
+	pub handle: Rc<Box<FileHandle>>,
+	/// File offset of the area's start address
+	pub offset: usize,
+	/// Number of bytes, which are read from the file. The rest
+	/// of the area is filled with zeros.
+	pub size: usize
+}
+
+#[derive(Clone, Debug)]
+pub struct Vma {
+	/// First address of the area
+	pub start: usize,
+	/// First address behind the area
+	pub end: usize,
+	/// Allowed accesses
+	pub flags: VmaFlags,
+	/// Backing file, anonymous areas are filled with zeros
+	pub file: Option<VmaFile>
+}
+
+impl Vma {
+	/// Returns the part [addr, end) of the area
+	fn tail(&self, addr: usize) -> Vma {
+		let skip = addr - self.start;
+
+		Vma {
+			start: addr,
+			end: self.end,
+			flags: self.flags,
+			file: self.file.as_ref().map(|file| VmaFile {
+				handle: file.handle.clone(),
+				offset: file.offset + skip,
+				size: file.size.saturating_sub(skip)
+			})
+		}
+	}
+
+	/// Checks if `next` continues this area and both can be merged
+	fn is_continued_by(&self, next: &Vma) -> bool {
+		self.end == next.start && self.flags == next.flags
+			&& self.file.is_none() && next.file.is_none()
+	}
+}
+
+#[derive(Clone)]
+pub struct VmaTable {
+	/// Areas sorted by their start address
+	areas: BTreeMap<usize, Vma>,
+	/// Start of the heap
+	brk_start: usize,
+	/// Current program break
+	brk: usize,
+	/// Maximum size of the stack (soft limit)
+	stack_limit: usize,
+	/// Upper bound of the soft limit (hard limit)
+	stack_limit_max: usize
+}
+
+impl VmaTable {
+	/// Create a table of an empty user space
+	pub fn new() -> Self {
+		VmaTable {
+			areas: BTreeMap::new(),
+			brk_start: 0,
+			brk: 0,
+			stack_limit: MAX_USER_STACK_SIZE,
+			stack_limit_max: MAX_USER_STACK_SIZE
+		}
+	}
+
+	/// Returns the area, which contains `addr`
+	pub fn find(&self, addr: usize) -> Option<Vma> {
+		match self.areas.range(..=addr).next_back() {
+			Some((_, vma)) if addr < vma.end => Some(vma.clone()),
+			_ => None
+		}
+	}
+
+	/// Checks if no area overlaps with [start, end)
+	fn is_free(&self, start: usize, end: usize) -> bool {
+		self.areas.range(..end).next_back().map_or(true, |(_, vma)| vma.end <= start)
+	}
+
+	/// Search the lowest free range of `len` bytes, which is located behind `MMAP_START`
+	fn find_free(&self, len: usize) -> Option<usize> {
+		let mut start = MMAP_START;
+
+		for vma in self.areas.values() {
+			if vma.end <= start {
+				continue;
+			}
+			if vma.start >= start + len {
+				break;
+			}
+			start = vma.end;
+		}
+
+		if start + len <= USER_SPACE_END {
+			Some(start)
+		} else {
+			None
+		}
+	}
+
+	/// Split the area, which contains `addr`, into [start, addr) and [addr, end)
+	fn split(&mut self, addr: usize) {
+		if let Some(vma) = self.find(addr) {
+			if vma.start < addr {
+				self.areas.insert(addr, vma.tail(addr));
+				self.areas.insert(vma.start, Vma { end: addr, ..vma });
+			}
+		}
+	}
+
+	/// Add the area and merge it with its neighbours, if they are
+	/// anonymous areas with the same flags. The range has to be free.
+	fn insert(&mut self, mut vma: Vma) {
+		let previous = self.areas.range(..vma.start).next_back().map(|(_, v)| v.clone());
+		if let Some(previous) = previous {
+			if previous.is_continued_by(&vma) {
+				self.areas.remove(&previous.start);
+				vma.start = previous.start;
+			}
+		}
+
+		let next = self.areas.get(&vma.end).cloned();
+		if let Some(next) = next {
+			if vma.is_continued_by(&next) {
+				self.areas.remove(&next.start);
+				vma.end = next.end;
+			}
+		}
+
+		self.areas.insert(vma.start, vma);
+	}
+
+	/// Map the area [start, end) and replace all areas, which overlap with it
+	pub fn map(&mut self, start: usize, end: usize, flags: VmaFlags, file: Option<VmaFile>) {
+		self.unmap(start, end);
+		self.insert(Vma { start: start, end: end, flags: flags, file: file });
+	}
+
+	/// Map an area of `len` bytes and return its start address. The area
+	/// is placed at `hint`, if this range is free.
+	pub fn map_anywhere(&mut self, hint: usize, len: usize, flags: VmaFlags, file: Option<VmaFile>) -> Result<usize> {
+		let start = if hint >= MMAP_START && hint.checked_add(len).map_or(false, |end| end <= USER_SPACE_END)
+			&& self.is_free(hint, hint + len) {
+			hint
+		} else {
+			self.find_free(len).ok_or(Error::OutOfMemory)?
+		};
+
+		self.insert(Vma { start: start, end: start + len, flags: flags, file: file });
+
+		Ok(start)
+	}
+
+	/// Remove the range [start, end) from all areas
+	pub fn unmap(&mut self, start: usize, end: usize) {
+		self.split(start);
+		self.split(end);
+
+		let keys: Vec<usize> = self.areas.range(start..end).map(|(k, _)| *k).collect();
+		for key in keys {
+			self.areas.remove(&key);
+		}
+	}
+
+	/// Change the flags of the range [start, end), which has to be
+	/// completely covered by areas
+	pub fn protect(&mut self, start: usize, end: usize, flags: VmaFlags) -> Result<()> {
+		let mut addr = start;
+		while addr < end {
+			match self.find(addr) {
+				Some(vma) => addr = vma.end,
+				None => return Err(Error::OutOfMemory)
+			}
+		}
+
+		// the growth direction and the kernel pages are properties of the area
+		// and not of the access rights
+		let properties = VmaFlags::GROWS_DOWN | VmaFlags::SPECIAL;
+		let flags = flags - properties;
+
+		self.split(start);
+		self.split(end);
+
+		let keys: Vec<usize> = self.areas.range(start..end).map(|(k, _)| *k).collect();
+		for key in keys {
+			// the area may be already merged with its predecessor
+			if let Some(mut vma) = self.areas.remove(&key) {
+				vma.flags = flags | (vma.flags & properties);
+				self.insert(vma);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Resize the range [start, old_end), which has to be part of a single
+	/// area, to [start, new_end). The area is only extended in place.
+	pub fn remap(&mut self, start: usize, old_end: usize, new_end: usize) -> Result<()> {
+		let vma = match self.find(start) {
+			Some(vma) if old_end <= vma.end => vma,
+			_ => return Err(Error::BadAddress)
+		};
+
+		if vma.flags.contains(VmaFlags::SPECIAL) {
+			return Err(Error::BadAddress);
+		}
+
+		if new_end <= old_end {
+			self.unmap(new_end, old_end);
+		} else {
+			if old_end != vma.end || new_end > USER_SPACE_END || !self.is_free(old_end, new_end) {
+				return Err(Error::OutOfMemory);
+			}
+
+			// a file mapping continues with the following part of the file
+			self.insert(Vma { end: new_end, ..vma.tail(old_end) });
+		}
+
+		Ok(())
+	}
+
+	/// Extend the stack area above `addr` down to the page of `addr` and
+	/// return the extended area. The stack can't exceed the stack limit and
+	/// has to keep a free guard page to the area below it.
+	pub fn expand_stack(&mut self, addr: usize) -> Option<Vma> {
+		let start = align_down!(addr, BasePageSize::SIZE);
+
+		let vma = match self.areas.range(addr..).next() {
+			Some((_, vma)) if vma.flags.contains(VmaFlags::GROWS_DOWN) => vma.clone(),
+			_ => return None
+		};
+
+		if vma.end - start > self.stack_limit || start < USER_SPACE_START + BasePageSize::SIZE
+			|| !self.is_free(start - BasePageSize::SIZE, vma.start) {
+			return None;
+		}
+
+		debug!("Expand stack from 0x{:x} down to 0x{:x}", vma.start, start);
+
+		self.areas.remove(&vma.start);
+		let vma = Vma { start: start, ..vma };
+		self.areas.insert(start, vma.clone());
+
+		Some(vma)
+	}
+
+	/// Returns the soft and the hard limit of the stack size
+	pub fn stack_limit(&self) -> (usize, usize) {
+		(self.stack_limit, self.stack_limit_max)
+	}
+
+	/// Set the limits of the stack size. The hard limit can't be raised.
+	pub fn set_stack_limit(&mut self, cur: usize, max: usize) -> Result<()> {
+		if cur > max {
+			return Err(Error::InvalidArgument);
+		}
+		if max > self.stack_limit_max {
+			return Err(Error::NotPermitted);
+		}
+
+		self.stack_limit = cur;
+		self.stack_limit_max = max;
+
+		Ok(())
+	}
+
+	/// Initialize the heap, which starts at `addr`
+	pub fn set_heap(&mut self, addr: usize) {
+		self.brk_start = addr;
+		self.brk = addr;
+	}
+
+	/// Move the program break to `addr` and return the previous and the
+	/// new break. If the break can't be moved, it stays unchanged.
+	pub fn set_break(&mut self, addr: usize) -> (usize, usize) {
+		let old = self.brk;
+
+		if self.brk_start == 0 || addr < self.brk_start || addr > INTERPRETER_START {
+			return (old, old);
+		}
+
+		let old_end = align_up!(old, BasePageSize::SIZE);
+		let new_end = align_up!(addr, BasePageSize::SIZE);
+
+		if new_end > old_end {
+			if !self.is_free(old_end, new_end) {
+				return (old, old);
+			}
+
+			self.insert(Vma { start: old_end, end: new_end, flags: VmaFlags::READ | VmaFlags::WRITE, file: None });
+		} else if new_end < old_end {
+			self.unmap(new_end, old_end);
+		}
+
+		self.brk = addr;
+
+		(old, addr)
+	}
+}
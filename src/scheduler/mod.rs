@@ -14,10 +14,17 @@ pub mod task;
 mod scheduler;
 
 use errno::*;
+use time;
+use alloc::boxed::Box;
 use alloc::rc::Rc;
 use core::cell::RefCell;
+use core::time::Duration;
 use scheduler::task::{TaskPriority, Task};
 use arch;
+use arch::irq::{irq_nested_disable,irq_nested_enable};
+use arch::AddressSpace;
+use fs::{FileHandle,FileRef};
+use mm::vma::{Vma,VmaFile,VmaFlags,VmaTable};
 
 static mut SCHEDULER: Option<scheduler::Scheduler> = None;
 
@@ -37,6 +44,14 @@ pub fn spawn(func: extern fn(), prio: TaskPriority) -> Result<task::TaskId> {
 	}
 }
 
+/// Create a copy of the current user-level task
+pub fn fork() -> Result<task::TaskId> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().fork()
+	}
+}
+
 /// Trigger the scheduler to switch to the next available task
 pub fn reschedule() {
 	unsafe {
@@ -65,22 +80,173 @@ pub fn abort() -> ! {
 	}
 }
 
+/// Restore the FPU state of the current task, triggered by
+/// the first FPU instruction after a task switch
+pub fn fpu_switch() {
+	unsafe {
+		SCHEDULER.as_mut().unwrap().fpu_switch()
+	}
+}
+
 pub fn get_current_stack() -> usize {
 	unsafe {
 		SCHEDULER.as_mut().unwrap().get_current_stack()
 	}
 }
 
+/// Check the kernel stacks of all tasks for an overflow and report their usage
+pub fn check_stacks() {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_ref().unwrap().check_stacks();
+	}
+}
+
+/// Returns the consumed CPU time of the current task in nanoseconds
+pub fn get_cpu_time() -> u64 {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_ref().unwrap().get_cpu_time()
+	}
+}
+
 pub fn get_root_page_table() -> usize {
 	unsafe {
 		SCHEDULER.as_mut().unwrap().get_root_page_table()
 	}
 }
 
-pub fn set_root_page_table(addr: usize) {
+pub fn set_address_space(space: AddressSpace) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().set_address_space(space);
+	}
+}
+
+/// Add `file` to the open files of the current task and return its descriptor
+pub fn insert_file(file: Box<FileHandle>) -> Result<i32> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().insert_file(file)
+	}
+}
+
+/// Returns the file, which is referenced by the descriptor `fd` of the current task
+pub fn get_file(fd: i32) -> Result<FileRef> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_ref().unwrap().get_file(fd)
+	}
+}
+
+/// Close the descriptor `fd` of the current task
+pub fn remove_file(fd: i32) -> Result<()> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().remove_file(fd)
+	}
+}
+
+/// Returns the virtual memory area of the current task, which contains `addr`
+pub fn find_vma(addr: usize) -> Option<Vma> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_ref().unwrap().find_vma(addr)
+	}
+}
+
+/// Replace the virtual memory areas of the current task
+pub fn set_vmas(vmas: VmaTable) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().set_vmas(vmas);
+	}
+}
+
+/// Add a virtual memory area of `len` bytes to the current task and return its start address
+pub fn map_vma(addr: usize, len: usize, flags: VmaFlags, file: Option<VmaFile>, fixed: bool) -> Result<usize> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().map_vma(addr, len, flags, file, fixed)
+	}
+}
+
+/// Remove the range [start, end) from the virtual memory areas of the current task
+pub fn unmap_vma(start: usize, end: usize) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().unmap_vma(start, end);
+	}
+}
+
+/// Change the allowed accesses to the range [start, end) of the current task
+pub fn protect_vma(start: usize, end: usize, flags: VmaFlags) -> Result<()> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().protect_vma(start, end, flags)
+	}
+}
+
+/// Resize the range [start, old_end) of the current task to [start, new_end)
+pub fn remap_vma(start: usize, old_end: usize, new_end: usize) -> Result<()> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().remap_vma(start, old_end, new_end)
+	}
+}
+
+/// Move the program break of the current task and return the previous and the new break
+pub fn set_break(addr: usize) -> (usize, usize) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().set_break(addr)
+	}
+}
+
+/// Extend the stack of the current task down to `addr` and return the stack area
+pub fn expand_stack(addr: usize) -> Option<Vma> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().expand_stack(addr)
+	}
+}
+
+/// Returns the soft and the hard limit of the current task's stack size
+pub fn get_stack_limit() -> (usize, usize) {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_ref().unwrap().get_stack_limit()
+	}
+}
+
+/// Set the limits of the current task's stack size
+pub fn set_stack_limit(cur: usize, max: usize) -> Result<()> {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		SCHEDULER.as_mut().unwrap().set_stack_limit(cur, max)
+	}
+}
+
+/// Block the current task until it is woken up or `wakeup_time` (in
+/// nanoseconds since boot time) is reached
+pub fn block_current_task_with_timeout(wakeup_time: u64) -> Rc<RefCell<Task>> {
+	unsafe {
+		SCHEDULER.as_mut().unwrap().block_current_task_with_timeout(wakeup_time)
+	}
+}
+
+/// Suspend the current task for at least `duration`
+pub fn sleep(duration: Duration) {
+	let wakeup_time = time::deadline(duration);
+
+	// the timer interrupt must not wake up the task before it is blocked
+	let flags = irq_nested_disable();
 	unsafe {
-		SCHEDULER.as_mut().unwrap().set_root_page_table(addr);
+		let scheduler = SCHEDULER.as_mut().unwrap();
+		scheduler.block_current_task_with_timeout(wakeup_time);
+		scheduler.reschedule();
 	}
+	irq_nested_enable(flags);
 }
 
 pub fn block_current_task() -> Rc<RefCell<Task>> {
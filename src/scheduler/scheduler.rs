@@ -9,14 +9,20 @@ use alloc::rc::Rc;
 use alloc::collections::{BTreeMap, VecDeque};
 use core::cell::RefCell;
 use core::sync::atomic::{AtomicU32, Ordering};
-use arch::drop_user_space;
+use core::mem;
+use arch;
+use arch::AddressSpace;
 use arch::irq::{irq_nested_enable,irq_nested_disable};
 use arch::switch;
+use alloc::boxed::Box;
+use fs::{FileHandle,FileRef};
+use mm::vma::{Vma,VmaFile,VmaFlags,VmaTable};
 use scheduler::task::*;
 use logging::*;
 use synch::spinlock::*;
 use consts::*;
 use errno::*;
+use time;
 
 static NO_TASKS: AtomicU32 = AtomicU32::new(0);
 static TID_COUNTER: AtomicU32 = AtomicU32::new(0);
@@ -26,12 +32,18 @@ pub struct Scheduler {
 	current_task:  Rc<RefCell<Task>>,
 	/// task id of the idle task
 	idle_task:  Rc<RefCell<Task>>,
+	/// task, which owns the current content of the FPU registers
+	fpu_owner: Rc<RefCell<Task>>,
 	/// queue of tasks, which are ready
 	ready_queue: SpinlockIrqSave<PriorityTaskQueue>,
 	/// queue of tasks, which are finished and can be released
 	finished_tasks: SpinlockIrqSave<VecDeque<TaskId>>,
 	// map between task id and task controll block
-	tasks: SpinlockIrqSave<BTreeMap<TaskId, Rc<RefCell<Task>>>>
+	tasks: SpinlockIrqSave<BTreeMap<TaskId, Rc<RefCell<Task>>>>,
+	/// timeouts of blocked tasks
+	timers: SpinlockIrqSave<TimerQueue>,
+	/// point of time of the last task switch in nanoseconds since boot time
+	last_switch: u64
 }
 
 impl Scheduler {
@@ -45,9 +57,12 @@ impl Scheduler {
 		Scheduler {
 			current_task: idle_task.clone(),
 			idle_task: idle_task.clone(),
+			fpu_owner: idle_task.clone(),
 			ready_queue: SpinlockIrqSave::new(PriorityTaskQueue::new()),
 			finished_tasks: SpinlockIrqSave::new(VecDeque::<TaskId>::new()),
-			tasks: tasks
+			tasks: tasks,
+			timers: SpinlockIrqSave::new(TimerQueue::new()),
+			last_switch: time::monotonic_ns()
 		}
 	}
 
@@ -84,12 +99,50 @@ impl Scheduler {
 		Ok(tid)
 	}
 
-	fn cleanup(&mut self) {
-		// destroy user space
-		drop_user_space();
+	/// Create a copy of the current user-level task, which
+	/// shares its pages by copy-on-write
+	pub fn fork(&mut self) -> Result<TaskId> {
+		// the FPU registers may contain a newer state than the task
+		if Rc::ptr_eq(&self.current_task, &self.fpu_owner) {
+			self.current_task.borrow_mut().last_fpu_state.save();
+		}
+
+		let tid = self.get_tid();
+		let task = {
+			let current = self.current_task.borrow();
+			let address_space = match current.address_space {
+				Some(ref space) => space.fork(),
+				None => return Err(Error::InvalidArgument)
+			};
+
+			let mut task = Task::new(tid, TaskStatus::TaskReady, current.prio);
+			task.address_space = Some(address_space);
+			task.last_fpu_state = current.last_fpu_state;
+			task.fds = current.fds.clone();
+			task.vmas = current.vmas.clone();
+			task.create_fork_frame(&current);
+
+			Rc::new(RefCell::new(task))
+		};
+
+		// Add it to the task lists.
+		self.ready_queue.lock().push(task.clone());
+		self.tasks.lock().insert(tid, task);
+		NO_TASKS.fetch_add(1, Ordering::SeqCst);
+
+		info!("Fork task {} from task {}", tid, self.current_task.borrow().id);
+
+		Ok(tid)
+	}
 
+	fn cleanup(&mut self) {
 		self.current_task.borrow_mut().status = TaskStatus::TaskFinished;
 
+		// the FPU state of a finished task is no longer required
+		if Rc::ptr_eq(&self.current_task, &self.fpu_owner) {
+			self.fpu_owner = self.idle_task.clone();
+		}
+
 		// update the number of tasks
 		NO_TASKS.fetch_sub(1, Ordering::SeqCst);
 	}
@@ -133,15 +186,61 @@ impl Scheduler {
 		}
 	}
 
+	/// Block the current task until it is woken up or `wakeup_time` is reached
+	pub fn block_current_task_with_timeout(&mut self, wakeup_time: u64) -> Rc<RefCell<Task>> {
+		let task = self.block_current_task();
+
+		task.borrow_mut().wakeup_time = Some(wakeup_time);
+		self.timers.lock().push(&task, wakeup_time);
+
+		task
+	}
+
 	pub fn wakeup_task(&mut self, task: Rc<RefCell<Task>>) {
 		if task.borrow().status == TaskStatus::TaskBlocked {
 			debug!("wakeup task {}", task.borrow().id);
 
-			task.borrow_mut().status = TaskStatus::TaskReady;
+			let mut borrowed = task.borrow_mut();
+			borrowed.status = TaskStatus::TaskReady;
+			// a pending timer is ignored
+			borrowed.wakeup_time = None;
+			drop(borrowed);
+
 			self.ready_queue.lock().push(task.clone());
 		}
 	}
 
+	/// Wake up all tasks, whose timeout is expired
+	fn wakeup_expired_tasks(&mut self) {
+		let now = time::monotonic_ns();
+
+		loop {
+			let task = match self.timers.lock().pop_expired(now) {
+				Some(task) => task,
+				None => break
+			};
+
+			// the task may be woken up before its timeout and blocked again
+			let expired = task.borrow().wakeup_time.map_or(false, |t| t <= now);
+			if expired {
+				self.wakeup_task(task);
+			}
+		}
+	}
+
+	/// Save the FPU state of the previous owner and restore the
+	/// FPU state of the current task
+	pub fn fpu_switch(&mut self) {
+		if !Rc::ptr_eq(&self.current_task, &self.fpu_owner) {
+			debug!("Switching FPU owner from task {} to {}",
+				self.fpu_owner.borrow().id, self.current_task.borrow().id);
+
+			self.fpu_owner.borrow_mut().last_fpu_state.save();
+			self.current_task.borrow().last_fpu_state.restore();
+			self.fpu_owner = self.current_task.clone();
+		}
+	}
+
 	pub fn get_current_taskid(&self) -> TaskId {
 		self.current_task.borrow().id
 	}
@@ -152,15 +251,104 @@ impl Scheduler {
 		unsafe { (*self.current_task.borrow().stack).bottom() }
 	}
 
+	/// Returns the consumed CPU time of the current task in nanoseconds
+	pub fn get_cpu_time(&self) -> u64 {
+		self.current_task.borrow().cpu_time + (time::monotonic_ns() - self.last_switch)
+	}
+
+	/// Add `file` to the open files of the current task and return its descriptor
+	pub fn insert_file(&mut self, file: Box<FileHandle>) -> Result<i32> {
+		self.current_task.borrow_mut().fds.insert(file)
+	}
+
+	/// Returns the file, which is referenced by the descriptor `fd` of the current task
+	pub fn get_file(&self, fd: i32) -> Result<FileRef> {
+		self.current_task.borrow().fds.get(fd)
+	}
+
+	/// Close the descriptor `fd` of the current task
+	pub fn remove_file(&mut self, fd: i32) -> Result<()> {
+		self.current_task.borrow_mut().fds.remove(fd)
+	}
+
+	/// Returns the virtual memory area of the current task, which contains `addr`
+	pub fn find_vma(&self, addr: usize) -> Option<Vma> {
+		self.current_task.borrow().vmas.find(addr)
+	}
+
+	/// Replace the virtual memory areas of the current task
+	pub fn set_vmas(&mut self, vmas: VmaTable) {
+		self.current_task.borrow_mut().vmas = vmas;
+	}
+
+	/// Add a virtual memory area of `len` bytes to the current task and return
+	/// its start address. With `fixed`, the area replaces all areas at `addr`.
+	pub fn map_vma(&mut self, addr: usize, len: usize, flags: VmaFlags, file: Option<VmaFile>, fixed: bool) -> Result<usize> {
+		let mut task = self.current_task.borrow_mut();
+
+		if fixed {
+			task.vmas.map(addr, addr + len, flags, file);
+			Ok(addr)
+		} else {
+			task.vmas.map_anywhere(addr, len, flags, file)
+		}
+	}
+
+	/// Remove the range [start, end) from the virtual memory areas of the current task
+	pub fn unmap_vma(&mut self, start: usize, end: usize) {
+		self.current_task.borrow_mut().vmas.unmap(start, end);
+	}
+
+	/// Change the allowed accesses to the range [start, end) of the current task
+	pub fn protect_vma(&mut self, start: usize, end: usize, flags: VmaFlags) -> Result<()> {
+		self.current_task.borrow_mut().vmas.protect(start, end, flags)
+	}
+
+	/// Resize the range [start, old_end) of the current task to [start, new_end)
+	pub fn remap_vma(&mut self, start: usize, old_end: usize, new_end: usize) -> Result<()> {
+		self.current_task.borrow_mut().vmas.remap(start, old_end, new_end)
+	}
+
+	/// Extend the stack of the current task down to `addr`
+	pub fn expand_stack(&mut self, addr: usize) -> Option<Vma> {
+		self.current_task.borrow_mut().vmas.expand_stack(addr)
+	}
+
+	/// Returns the soft and the hard limit of the current task's stack size
+	pub fn get_stack_limit(&self) -> (usize, usize) {
+		self.current_task.borrow().vmas.stack_limit()
+	}
+
+	/// Set the limits of the current task's stack size
+	pub fn set_stack_limit(&mut self, cur: usize, max: usize) -> Result<()> {
+		self.current_task.borrow_mut().vmas.set_stack_limit(cur, max)
+	}
+
+	/// Move the program break of the current task and return the previous and the new break
+	pub fn set_break(&mut self, addr: usize) -> (usize, usize) {
+		self.current_task.borrow_mut().vmas.set_break(addr)
+	}
+
 	pub fn get_root_page_table(&self) -> usize {
-		self.current_task.borrow().root_page_table
+		match self.current_task.borrow().address_space {
+			Some(ref space) => space.root_page_table(),
+			None => arch::get_kernel_root_page_table()
+		}
 	}
 
-	pub fn set_root_page_table(&self, addr: usize) {
-		self.current_task.borrow_mut().root_page_table = addr;
+	/// Replace the address space of the current task and activate the new one.
+	/// The previous address space is destroyed, after the switch to the new one.
+	pub fn set_address_space(&mut self, space: AddressSpace) {
+		let _old = {
+			let mut task = self.current_task.borrow_mut();
+			space.activate();
+			mem::replace(&mut task.address_space, Some(space))
+		};
 	}
 
 	pub fn schedule(&mut self) {
+		self.wakeup_expired_tasks();
+
 		// do we have finished tasks? => drop tasks => deallocate implicitly the stack
 		match self.finished_tasks.lock().pop_front() {
 			Some(id) => {
@@ -215,6 +403,13 @@ impl Scheduler {
 					self.finished_tasks.lock().push_back(current_id);
 				}
 
+				// detect a stack overflow before the task leaves the processor
+				self.current_task.borrow().check_stack();
+
+				let now = time::monotonic_ns();
+				self.current_task.borrow_mut().cpu_time += now - self.last_switch;
+				self.last_switch = now;
+
 				debug!("Switching task from {} to {} (stack {:#X} => {:#X})", current_id, new_id,
 					unsafe { *current_stack_pointer }, new_stack_pointer);
 
@@ -226,6 +421,17 @@ impl Scheduler {
 		}
 	}
 
+	/// Check the kernel stacks of all tasks and report their high-water marks
+	pub fn check_stacks(&self) {
+		for task in self.tasks.lock().values() {
+			let task = task.borrow();
+
+			task.check_stack();
+			info!("Task {} used {} of {} bytes of its kernel stack",
+				task.id, task.stack_high_water_mark(), STACK_SIZE);
+		}
+	}
+
 	pub fn reschedule(&mut self) {
 		let flags = irq_nested_disable();
 		self.schedule();
@@ -8,13 +8,16 @@
 #![allow(dead_code)]
 
 use alloc;
-use alloc::rc::Rc;
+use alloc::rc::{Rc,Weak};
+use alloc::collections::BinaryHeap;
+use core::cmp::Ordering;
 use core::cell::RefCell;
 use core::fmt;
-use alloc::alloc::{alloc, dealloc, Layout};
-use arch;
-use arch::processor::msb;
-use arch::{PageSize,BasePageSize};
+use arch::processor::{msb,FPUState};
+use arch::AddressSpace;
+use fs::FileDescriptorTable;
+use mm;
+use mm::vma::VmaTable;
 use logging::*;
 use consts::*;
 
@@ -251,6 +254,80 @@ impl PriorityTaskQueue {
 	}
 }
 
+/// Timeout of a blocked task
+struct Timer {
+	/// Point of time in nanoseconds, when the task is woken up
+	wakeup_time: u64,
+	/// The timer doesn't keep a finished task alive
+	task: Weak<RefCell<Task>>
+}
+
+impl PartialEq for Timer {
+	fn eq(&self, other: &Self) -> bool {
+		self.wakeup_time == other.wakeup_time
+	}
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Timer {
+	/// The earliest timeout is the greatest element of the max-heap
+	fn cmp(&self, other: &Self) -> Ordering {
+		other.wakeup_time.cmp(&self.wakeup_time)
+	}
+}
+
+/// Realize a heap of pending timeouts, which is sorted by the wakeup time.
+/// A timer isn't removed, if its task is woken up before the timeout.
+/// Instead, the task's `wakeup_time` is reset and the timer is ignored
+/// when it expires. Because a timer references its task only weakly, the
+/// task may terminate and be released before its timer expires.
+pub struct TimerQueue {
+	heap: BinaryHeap<Timer>
+}
+
+impl TimerQueue {
+	/// Creates an empty timer queue
+	pub fn new() -> TimerQueue {
+		TimerQueue {
+			heap: BinaryHeap::new()
+		}
+	}
+
+	/// Add a timer, which wakes up `task` at `wakeup_time`
+	pub fn push(&mut self, task: &Rc<RefCell<Task>>, wakeup_time: u64) {
+		self.heap.push(Timer { wakeup_time: wakeup_time, task: Rc::downgrade(task) });
+	}
+
+	/// Pop a task, whose timeout expires at or before `now`. Timers of
+	/// released tasks are dropped.
+	pub fn pop_expired(&mut self, now: u64) -> Option<Rc<RefCell<Task>>> {
+		loop {
+			match self.heap.peek() {
+				Some(timer) if timer.wakeup_time <= now => {},
+				_ => return None
+			}
+
+			if let Some(task) = self.heap.pop().and_then(|timer| timer.task.upgrade()) {
+				return Some(task);
+			}
+		}
+	}
+}
+
+/// Pattern, which fills the unused part of a kernel stack
+pub const STACK_PATTERN: u8 = 0xCD;
+
+/// Number of bytes at the bottom of a kernel stack, which have to keep
+/// the pattern. An overwritten byte indicates a stack overflow.
+const STACK_CANARY_SIZE: usize = 256;
+
 #[derive(Copy, Clone)]
 #[repr(align(64))]
 #[repr(C)]
@@ -261,10 +338,21 @@ pub struct Stack {
 impl Stack {
 	pub const fn new() -> Stack {
 		Stack {
-			buffer: [0; STACK_SIZE]
+			buffer: [STACK_PATTERN; STACK_SIZE]
 		}
 	}
 
+	/// Checks if the canary at the bottom of the stack is intact
+	pub fn is_intact(&self) -> bool {
+		self.buffer[..STACK_CANARY_SIZE].iter().all(|b| *b == STACK_PATTERN)
+	}
+
+	/// Returns the maximum number of bytes, which are used since the
+	/// creation of the stack
+	pub fn high_water_mark(&self) -> usize {
+		STACK_SIZE - self.buffer.iter().take_while(|b| **b == STACK_PATTERN).count()
+	}
+
 	pub fn top(&self) -> usize {
 		(&(self.buffer[STACK_SIZE - 16]) as *const _) as usize
 	}
@@ -289,8 +377,18 @@ pub struct Task {
 	pub last_stack_pointer: usize,
 	// Stack of the task
 	pub stack: *mut Stack,
-	// Physical address of the 1st level page table
-	pub root_page_table: usize,
+	/// Page tables of a user-level task, kernel tasks use the kernel page tables
+	pub address_space: Option<AddressSpace>,
+	/// Stored FPU state of the task
+	pub last_fpu_state: FPUState,
+	/// Open files of the task
+	pub fds: FileDescriptorTable,
+	/// Virtual memory areas of the user space
+	pub vmas: VmaTable,
+	/// Timeout of a blocked task in nanoseconds since boot time
+	pub wakeup_time: Option<u64>,
+	/// Consumed CPU time in nanoseconds
+	pub cpu_time: u64,
 	// next task in queue
 	pub next: Option<Rc<RefCell<Task>>>,
 	// previous task in queue
@@ -305,14 +403,20 @@ impl Task {
 			status: TaskStatus::TaskIdle,
 			last_stack_pointer: 0,
 			stack: unsafe { &mut BOOT_STACK },
-			root_page_table: arch::get_kernel_root_page_table(),
+			address_space: None,
+			last_fpu_state: FPUState::new(),
+			fds: FileDescriptorTable::new(),
+			vmas: VmaTable::new(),
+			wakeup_time: None,
+			cpu_time: 0,
 			next: None,
 			prev: None
 		}
 	}
 
 	pub fn new(id: TaskId, status: TaskStatus, prio: TaskPriority) -> Task {
-		let stack = unsafe { alloc(Layout::new::<Stack>()) as *mut Stack };
+		// an overflow of the stack hits the unmapped guard page below it
+		let stack = mm::allocate_guarded(STACK_SIZE) as *mut Stack;
 
 		debug!("Allocate stack for task {} at 0x{:x}", id, stack as usize);
 
@@ -322,30 +426,49 @@ impl Task {
 			status: status,
 			last_stack_pointer: 0,
 			stack: stack,
-			root_page_table: arch::get_kernel_root_page_table(),
+			address_space: None,
+			last_fpu_state: FPUState::new(),
+			fds: FileDescriptorTable::new(),
+			vmas: VmaTable::new(),
+			wakeup_time: None,
+			cpu_time: 0,
 			next: None,
 			prev: None
 		}
 	}
 }
 
+impl Task {
+	/// Panics, if the task has overwritten the canary of its kernel stack
+	pub fn check_stack(&self) {
+		if unsafe { !(*self.stack).is_intact() } {
+			panic!("Kernel stack overflow of task {} (stack at 0x{:x})", self.id, self.stack as usize);
+		}
+	}
+
+	/// Returns the maximum number of bytes, which the task has used on its kernel stack
+	pub fn stack_high_water_mark(&self) -> usize {
+		unsafe { (*self.stack).high_water_mark() }
+	}
+}
+
 pub trait TaskFrame {
 	/// Create the initial stack frame for a new task
 	fn create_stack_frame(&mut self, func: extern fn());
+
+	/// Create the initial stack frame for a forked task, which
+	/// leaves the system call of its parent
+	fn create_fork_frame(&mut self, parent: &Task);
 }
 
 impl Drop for Task {
 	fn drop(&mut self) {
 		if unsafe { self.stack != &mut BOOT_STACK } {
-			debug!("Deallocate stack of task {} (stack at 0x{:x})", self.id, self.stack as usize);
+			debug!("Deallocate stack of task {} (stack at 0x{:x}, {} of {} bytes used)",
+				self.id, self.stack as usize, self.stack_high_water_mark(), STACK_SIZE);
 
 			// deallocate stack
-			unsafe { dealloc(self.stack as *mut u8, Layout::new::<Stack>()); }
-		}
-
-		if self.root_page_table != arch::get_kernel_root_page_table() {
-			debug!("Deallocate page table 0x{:x} of task {}", self.root_page_table, self.id);
-			arch::mm::physicalmem::deallocate(self.root_page_table, BasePageSize::SIZE);
+			mm::deallocate_guarded(self.stack as usize, STACK_SIZE);
 		}
 	}
 }
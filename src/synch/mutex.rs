@@ -5,12 +5,57 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use core::cell::UnsafeCell;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{RefCell, UnsafeCell};
 use core::ops::{Drop, Deref, DerefMut};
 use core::marker::Sync;
+use core::time::Duration;
 use scheduler::task::*;
-use scheduler::{wakeup_task,reschedule,block_current_task};
+use scheduler::{wakeup_task,reschedule,block_current_task,block_current_task_with_timeout};
 use synch::spinlock::*;
+use errno::*;
+use time;
+
+/// Tasks, which wait for a mutex. In contrast to the ready queue, the
+/// tasks aren't linked, because a task with a timeout may be woken up by
+/// the timer and added to the ready queue, while it is still waiting.
+struct WaitQueue {
+	tasks: Vec<Rc<RefCell<Task>>>
+}
+
+impl WaitQueue {
+	fn new() -> Self {
+		WaitQueue {
+			tasks: Vec::new()
+		}
+	}
+
+	fn push(&mut self, task: Rc<RefCell<Task>>) {
+		self.tasks.push(task);
+	}
+
+	/// Pop the first blocked task with the highest priority. Tasks, which
+	/// are already woken up by their timeout, are skipped.
+	fn pop(&mut self) -> Option<Rc<RefCell<Task>>> {
+		let mut index = None;
+		let mut prio = LOW_PRIORITY;
+
+		for (i, task) in self.tasks.iter().enumerate() {
+			let task = task.borrow();
+			if task.status == TaskStatus::TaskBlocked && (index.is_none() || task.prio > prio) {
+				index = Some(i);
+				prio = task.prio;
+			}
+		}
+
+		index.map(|i| self.tasks.remove(i))
+	}
+
+	fn remove(&mut self, task: &Rc<RefCell<Task>>) {
+		self.tasks.retain(|t| !Rc::ptr_eq(t, task));
+	}
+}
 
 /// A mutual exclusion primitive useful for protecting shared data
 ///
@@ -44,8 +89,8 @@ use synch::spinlock::*;
 pub struct Mutex<T: ?Sized> {
 	/// in principle a binary semaphore
 	value: SpinlockIrqSave<bool>,
-	/// Priority queue of waiting tasks
-	queue: SpinlockIrqSave<PriorityTaskQueue>,
+	/// Waiting tasks
+	queue: SpinlockIrqSave<WaitQueue>,
 	/// protected data
 	data: UnsafeCell<T>
 }
@@ -55,7 +100,7 @@ pub struct Mutex<T: ?Sized> {
 /// When the guard falls out of scope it will release the lock.
 pub struct MutexGuard<'a, T: ?Sized + 'a> {
 	value: &'a SpinlockIrqSave<bool>,
-	queue: &'a SpinlockIrqSave<PriorityTaskQueue>,
+	queue: &'a SpinlockIrqSave<WaitQueue>,
 	data: &'a mut T,
 }
 
@@ -72,7 +117,7 @@ impl<T> Mutex<T> {
 	pub fn new(user_data: T) -> Mutex<T> {
 		Mutex {
 			value: SpinlockIrqSave::new(true),
-			queue: SpinlockIrqSave::new(PriorityTaskQueue::new()),
+			queue: SpinlockIrqSave::new(WaitQueue::new()),
 			data: UnsafeCell::new(user_data)
 		}
 	}
@@ -105,6 +150,29 @@ impl<T: ?Sized> Mutex<T>
 		}
 	}
 
+	/// Wait for the lock until `wakeup_time` (in nanoseconds since boot time)
+	fn obtain_lock_with_timeout(&self, wakeup_time: u64) -> Result<()> {
+		loop {
+			let mut count = self.value.lock();
+
+			if *count == true {
+				*count = false;
+				return Ok(());
+			} else if time::monotonic_ns() >= wakeup_time {
+				return Err(Error::TimedOut);
+			} else {
+				let task = block_current_task_with_timeout(wakeup_time);
+				self.queue.lock().push(task.clone());
+				// release lock
+				drop(count);
+				// switch to the next task
+				reschedule();
+				// the task is still queued, if the timeout is expired
+				self.queue.lock().remove(&task);
+			}
+		}
+	}
+
 	pub fn lock(&self) -> MutexGuard<T>
 	{
 		self.obtain_lock();
@@ -115,6 +183,19 @@ impl<T: ?Sized> Mutex<T>
 			data: unsafe { &mut *self.data.get() },
 		}
 	}
+
+	/// Acquire the lock, but wait at most `timeout`. Returns
+	/// `Error::TimedOut`, if the lock isn't released in time.
+	pub fn lock_with_timeout(&self, timeout: Duration) -> Result<MutexGuard<T>>
+	{
+		self.obtain_lock_with_timeout(time::deadline(timeout))?;
+		Ok(MutexGuard
+		{
+			value: &self.value,
+			queue: &self.queue,
+			data: unsafe { &mut *self.data.get() },
+		})
+	}
 }
 
 impl<T: ?Sized + Default> Default for Mutex<T> {
@@ -0,0 +1,52 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use arch::processor::{readfs,writefs,read_user_gs,write_user_gs};
+use consts::*;
+use mm::user::put_user;
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// set the user-level GS base
+const ARCH_SET_GS: i32 = 0x1001;
+/// set the FS base
+const ARCH_SET_FS: i32 = 0x1002;
+/// get the FS base
+const ARCH_GET_FS: i32 = 0x1003;
+/// get the user-level GS base
+const ARCH_GET_GS: i32 = 0x1004;
+
+fn arch_prctl(code: i32, addr: usize) -> Result<()> {
+	match code {
+		ARCH_SET_FS | ARCH_SET_GS if addr >= USER_SPACE_END => Err(Error::NotPermitted),
+		ARCH_SET_FS => {
+			debug!("Set FS base to 0x{:x}", addr);
+			writefs(addr);
+			Ok(())
+		},
+		ARCH_SET_GS => {
+			debug!("Set GS base to 0x{:x}", addr);
+			write_user_gs(addr);
+			Ok(())
+		},
+		ARCH_GET_FS => put_user(addr, readfs()),
+		ARCH_GET_GS => put_user(addr, read_user_gs()),
+		_ => {
+			error!("Unsupported arch_prctl code 0x{:x}", code);
+			Err(Error::InvalidArgument)
+		}
+	}
+}
+
+/// Set or get the FS and GS base of the current task.
+/// The context switch saves both values and, consequently, they are task specific.
+#[no_mangle]
+pub extern "C" fn sys_arch_prctl(code: i32, addr: usize) -> isize
+{
+	syscall_result(arch_prctl(code, addr).map(|_| 0))
+}
@@ -0,0 +1,29 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use arch::mm::paging::{BasePageSize,PageSize,unmap_user_pages};
+use scheduler;
+use logging::*;
+
+/// Move the program break to `addr`. Like Linux, the system call returns
+/// the new break and the current break, if the break can't be moved.
+#[no_mangle]
+pub extern "C" fn sys_brk(addr: usize) -> isize
+{
+	let (old, new) = scheduler::set_break(addr);
+
+	debug!("brk(0x{:x}) = 0x{:x}", addr, new);
+
+	// release the pages behind the new break
+	let old_end = align_up!(old, BasePageSize::SIZE);
+	let new_end = align_up!(new, BasePageSize::SIZE);
+	if new_end < old_end {
+		unmap_user_pages(new_end, old_end);
+	}
+
+	new as isize
+}
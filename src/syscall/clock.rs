@@ -0,0 +1,95 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use mm::user::put_user;
+use time::*;
+use scheduler;
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// Time value of `gettimeofday`, which is identical to `struct timeval`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Timeval {
+	tv_sec: i64,
+	tv_usec: i64
+}
+
+/// Time zone of `gettimeofday`, which is identical to `struct timezone`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Timezone {
+	tz_minuteswest: i32,
+	tz_dsttime: i32
+}
+
+/// Returns the current value of `clock` in nanoseconds. The kernel runs
+/// only one task at a time, so the CPU time of a process and a thread is
+/// identical.
+fn get_clock_ns(clock: i32) -> Result<u64> {
+	match clock {
+		CLOCK_REALTIME | CLOCK_REALTIME_COARSE => Ok(realtime_ns()),
+		CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE
+			| CLOCK_BOOTTIME => Ok(monotonic_ns()),
+		CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => Ok(scheduler::get_cpu_time()),
+		_ => {
+			error!("Unsupported clock {}", clock);
+			Err(Error::InvalidArgument)
+		}
+	}
+}
+
+fn clock_gettime(clock: i32, tp: usize) -> Result<()> {
+	put_user(tp, Timespec::from_ns(get_clock_ns(clock)?))
+}
+
+fn gettimeofday(tv: usize, tz: usize) -> Result<()> {
+	let ns = realtime_ns();
+
+	if tv != 0 {
+		put_user(tv, Timeval {
+			tv_sec: (ns / NSEC_PER_SEC) as i64,
+			tv_usec: ((ns % NSEC_PER_SEC) / 1000) as i64
+		})?;
+	}
+
+	// the clock is always UTC
+	if tz != 0 {
+		put_user(tz, Timezone { tz_minuteswest: 0, tz_dsttime: 0 })?;
+	}
+
+	Ok(())
+}
+
+fn time(tloc: usize) -> Result<usize> {
+	let seconds = realtime_ns() / NSEC_PER_SEC;
+
+	if tloc != 0 {
+		put_user(tloc, seconds as i64)?;
+	}
+
+	Ok(seconds as usize)
+}
+
+#[no_mangle]
+pub extern "C" fn sys_clock_gettime(clock: i32, tp: usize) -> isize
+{
+	syscall_result(clock_gettime(clock, tp).map(|_| 0))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_gettimeofday(tv: usize, tz: usize) -> isize
+{
+	syscall_result(gettimeofday(tv, tz).map(|_| 0))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_time(tloc: usize) -> isize
+{
+	syscall_result(time(tloc))
+}
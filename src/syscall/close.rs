@@ -0,0 +1,15 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use scheduler;
+use syscall::syscall_result;
+
+#[no_mangle]
+pub extern "C" fn sys_close(fd: i32) -> isize
+{
+	syscall_result(scheduler::remove_file(fd).map(|_| 0))
+}
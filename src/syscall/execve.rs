@@ -0,0 +1,64 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use arch;
+use mm::user::{copy_string_from_user,get_user};
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// Maximum length of a path or an argument
+const MAX_STRING_LENGTH: usize = 4096;
+
+/// Maximum number of arguments or environment variables
+const MAX_ARGUMENTS: usize = 1024;
+
+/// Copy a null-terminated array of strings from the user space
+fn copy_string_array(addr: usize) -> Result<Vec<String>> {
+	let mut strings = Vec::new();
+
+	// a null pointer is handled as empty array
+	if addr == 0 {
+		return Ok(strings);
+	}
+
+	loop {
+		let s: usize = get_user(addr + strings.len() * size_of::<usize>())?;
+		if s == 0 {
+			return Ok(strings);
+		}
+
+		if strings.len() >= MAX_ARGUMENTS {
+			return Err(Error::ArgumentListTooLong);
+		}
+
+		strings.push(copy_string_from_user(s, MAX_STRING_LENGTH, Error::ArgumentListTooLong)?);
+	}
+}
+
+fn execve(path: usize, argv: usize, envp: usize) -> Result<()> {
+	let path = copy_string_from_user(path, MAX_STRING_LENGTH, Error::NameTooLong)?;
+	let argv = copy_string_array(argv)?;
+	let envp = copy_string_array(envp)?;
+
+	arch::execve(path, argv, envp)
+}
+
+#[no_mangle]
+pub extern "C" fn sys_execve(path: usize, argv: usize, envp: usize) -> isize
+{
+	// returns only in case of an error
+	let result = execve(path, argv, envp);
+	if let Err(ref e) = result {
+		debug!("execve failed: {}", e);
+	}
+
+	syscall_result(result.map(|_| 0))
+}
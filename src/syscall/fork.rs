@@ -0,0 +1,38 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use logging::*;
+use scheduler;
+use errno::*;
+use syscall::syscall_result;
+
+/// share the address space (threads aren't supported)
+const CLONE_VM: u64 = 0x00000100;
+/// same thread group (threads aren't supported)
+const CLONE_THREAD: u64 = 0x00010000;
+
+#[no_mangle]
+pub extern "C" fn sys_fork() -> isize
+{
+	let result = scheduler::fork();
+	if let Err(ref e) = result {
+		error!("Unable to fork task {}: {}", scheduler::get_current_taskid(), e);
+	}
+
+	syscall_result(result.map(|tid| tid.into() as usize))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_clone(flags: u64, stack: usize) -> isize
+{
+	if flags & (CLONE_VM | CLONE_THREAD) != 0 || stack != 0 {
+		error!("Unsupported clone flags 0x{:x} (stack 0x{:x})", flags, stack);
+		return -EINVAL as isize;
+	}
+
+	sys_fork()
+}
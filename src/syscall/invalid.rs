@@ -5,17 +5,21 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use errno::*;
 use logging::*;
-use scheduler::*;
 
+#[no_mangle]
+extern "C" fn invalid_syscall(sysno: u64) -> isize
+{
+	error!("Invalid syscall {}", sysno);
+	-ENOSYS as isize
+}
+
+/// Handler of unimplemented system calls, which passes the
+/// system call number in rax to `invalid_syscall`
 #[no_mangle]
 #[naked]
 pub unsafe extern "C" fn sys_invalid()
 {
-	let mut rax: i64 = 0;
-
-	asm!("push %rax; pop $0" : "=r"(rax));
-
-	error!("Invalid syscall {}", rax);
-	do_exit();
+	asm!("mov %rax, %rdi; jmp invalid_syscall" :::: "volatile");
 }
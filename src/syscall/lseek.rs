@@ -0,0 +1,37 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use fs::SeekFrom;
+use scheduler;
+use syscall::syscall_result;
+use errno::*;
+
+/// the offset is set to `offset` bytes
+const SEEK_SET: i32 = 0;
+/// the offset is set to its current location plus `offset` bytes
+const SEEK_CUR: i32 = 1;
+/// the offset is set to the size of the file plus `offset` bytes
+const SEEK_END: i32 = 2;
+
+fn lseek(fd: i32, offset: i64, whence: i32) -> Result<u64> {
+	let style = match whence {
+		SEEK_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+		SEEK_CUR => SeekFrom::Current(offset),
+		SEEK_END => SeekFrom::End(offset),
+		_ => return Err(Error::InvalidArgument)
+	};
+
+	let file = scheduler::get_file(fd)?;
+	let result = file.lock().seek(style);
+	result
+}
+
+#[no_mangle]
+pub extern "C" fn sys_lseek(fd: i32, offset: i64, whence: i32) -> isize
+{
+	syscall_result(lseek(fd, offset, whence).map(|pos| pos as usize))
+}
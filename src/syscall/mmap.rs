@@ -0,0 +1,184 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use arch::mm::paging::{BasePageSize,PageSize,unmap_user_pages,protect_user_pages};
+use mm::user::is_user_range;
+use alloc::rc::Rc;
+use mm::vma::{VmaFile,VmaFlags};
+use scheduler;
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// changes are shared with other mappings of the same file
+const MAP_SHARED: i32 = 0x01;
+/// changes are private to the process
+const MAP_PRIVATE: i32 = 0x02;
+/// the mapping is placed exactly at the given address
+const MAP_FIXED: i32 = 0x10;
+/// the mapping isn't backed by a file
+const MAP_ANONYMOUS: i32 = 0x20;
+/// the mapping may be moved to a new address
+const MREMAP_MAYMOVE: i32 = 0x01;
+
+/// Convert the protection flags `prot` of a system call
+fn protection(prot: i32) -> Result<VmaFlags> {
+	match VmaFlags::from_bits(prot as u32) {
+		// only the kernel creates special areas
+		Some(flags) if !flags.contains(VmaFlags::SPECIAL) => Ok(flags),
+		_ => Err(Error::InvalidArgument)
+	}
+}
+
+/// Round `len` up to a multiple of the page size
+fn page_align(len: usize) -> Result<usize> {
+	if len == 0 {
+		return Err(Error::InvalidArgument);
+	}
+
+	match len.checked_add(BasePageSize::SIZE - 1) {
+		Some(len) => Ok(len & !(BasePageSize::SIZE - 1)),
+		None => Err(Error::OutOfMemory)
+	}
+}
+
+/// Checks that [addr, addr + len) is a page-aligned range of the user space
+/// and returns the end of the range, which is rounded up to the next page
+fn check_range(addr: usize, len: usize) -> Result<usize> {
+	let len = page_align(len)?;
+
+	if addr % BasePageSize::SIZE != 0 || !is_user_range(addr, len) {
+		return Err(Error::InvalidArgument);
+	}
+
+	Ok(addr + len)
+}
+
+/// Create the backing of a mapping of the file `fd` at `offset`
+fn map_file(fd: i32, offset: i64) -> Result<VmaFile> {
+	if offset < 0 || offset as usize % BasePageSize::SIZE != 0 {
+		return Err(Error::InvalidArgument);
+	}
+
+	// the mapping gets its own handle, which is independent of the file position
+	let file = scheduler::get_file(fd)?;
+	let handle = file.lock().duplicate()?;
+
+	Ok(VmaFile {
+		handle: Rc::new(handle),
+		offset: offset as usize,
+		size: usize::max_value()
+	})
+}
+
+fn mmap(addr: usize, len: usize, prot: i32, flags: i32, fd: i32, offset: i64) -> Result<usize> {
+	let prot = protection(prot)?;
+
+	// only the stack grows down
+	if prot.contains(VmaFlags::GROWS_DOWN) {
+		return Err(Error::InvalidArgument);
+	}
+
+	match flags & (MAP_SHARED | MAP_PRIVATE) {
+		MAP_PRIVATE => {},
+		// without write access, a shared mapping behaves like a private one
+		MAP_SHARED if flags & MAP_ANONYMOUS == 0 && !prot.contains(VmaFlags::WRITE) => {},
+		_ => {
+			error!("Unsupported mapping (flags 0x{:x}, protection {:?})", flags, prot);
+			return Err(Error::InvalidArgument);
+		}
+	}
+
+	let file = if flags & MAP_ANONYMOUS == 0 {
+		Some(map_file(fd, offset)?)
+	} else {
+		None
+	};
+
+	if flags & MAP_FIXED != 0 {
+		let end = check_range(addr, len)?;
+
+		// the new mapping replaces all pages in the range
+		unmap_user_pages(addr, end);
+		scheduler::map_vma(addr, end - addr, prot, file, true)
+	} else {
+		let len = page_align(len)?;
+
+		// the hint is only used, if it's a valid address
+		let hint = if addr % BasePageSize::SIZE == 0 { addr } else { 0 };
+		scheduler::map_vma(hint, len, prot, file, false)
+	}
+}
+
+fn munmap(addr: usize, len: usize) -> Result<()> {
+	let end = check_range(addr, len)?;
+
+	scheduler::unmap_vma(addr, end);
+	unmap_user_pages(addr, end);
+
+	Ok(())
+}
+
+/// Resize the mapping at `addr`. The mapping is never moved, but a
+/// failed resize lets the C library fall back to a new mapping.
+fn mremap(addr: usize, old_len: usize, new_len: usize, flags: i32) -> Result<usize> {
+	if flags & !MREMAP_MAYMOVE != 0 {
+		return Err(Error::InvalidArgument);
+	}
+
+	let old_end = check_range(addr, old_len)?;
+	let new_end = check_range(addr, new_len)?;
+
+	scheduler::remap_vma(addr, old_end, new_end)?;
+	if new_end < old_end {
+		unmap_user_pages(new_end, old_end);
+	}
+
+	Ok(addr)
+}
+
+fn mprotect(addr: usize, len: usize, prot: i32) -> Result<()> {
+	// PROT_GROWSDOWN is accepted, but the stack keeps its range
+	let prot = protection(prot)? - VmaFlags::GROWS_DOWN;
+	let end = check_range(addr, len)?;
+
+	scheduler::protect_vma(addr, end, prot)?;
+	protect_user_pages(addr, end, prot.page_flags());
+
+	Ok(())
+}
+
+/// Map anonymous memory or a file into the user space. Changes of
+/// file mappings are private and aren't written back to the file.
+#[no_mangle]
+pub extern "C" fn sys_mmap(addr: usize, len: usize, prot: i32, flags: i32, fd: i32, offset: i64) -> isize
+{
+	debug!("mmap(0x{:x}, 0x{:x}, 0x{:x}, 0x{:x}, {}, {})", addr, len, prot, flags, fd, offset);
+
+	syscall_result(mmap(addr, len, prot, flags, fd, offset))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_munmap(addr: usize, len: usize) -> isize
+{
+	syscall_result(munmap(addr, len).map(|_| 0))
+}
+
+/// Resize a mapping. Special areas like the vDSO can't be resized.
+#[no_mangle]
+pub extern "C" fn sys_mremap(addr: usize, old_len: usize, new_len: usize, flags: i32) -> isize
+{
+	debug!("mremap(0x{:x}, 0x{:x}, 0x{:x}, 0x{:x})", addr, old_len, new_len, flags);
+
+	syscall_result(mremap(addr, old_len, new_len, flags))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_mprotect(addr: usize, len: usize, prot: i32) -> isize
+{
+	syscall_result(mprotect(addr, len, prot).map(|_| 0))
+}
@@ -5,40 +5,144 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+mod read;
 mod write;
+mod open;
+mod close;
+mod lseek;
 mod exit;
 mod invalid;
 mod nothing;
+mod fork;
+mod execve;
+mod arch_prctl;
+mod mmap;
+mod brk;
+mod rlimit;
+mod nanosleep;
+mod clock;
 
 use syscall::exit::sys_exit;
-use syscall::write::{sys_write,sys_writev};
+use syscall::read::{sys_read,sys_pread64,sys_readv};
+use syscall::write::{sys_write,sys_writev,sys_pwrite64};
+use syscall::open::sys_open;
+use syscall::close::sys_close;
+use syscall::lseek::sys_lseek;
 use syscall::invalid::sys_invalid;
 use syscall::nothing::sys_nothing;
+use syscall::fork::{sys_fork,sys_clone};
+use syscall::execve::sys_execve;
+use syscall::arch_prctl::sys_arch_prctl;
+use syscall::mmap::{sys_mmap,sys_munmap,sys_mprotect,sys_mremap};
+use syscall::brk::sys_brk;
+use syscall::clock::{sys_clock_gettime,sys_gettimeofday,sys_time};
+use syscall::nanosleep::{sys_nanosleep,sys_clock_nanosleep};
+use syscall::rlimit::{sys_getrlimit,sys_setrlimit,sys_prlimit64};
+use errno::*;
+
+/// number of the system call `read`
+pub const SYSNO_READ: usize = 0;
 
 /// number of the system call `write`
 pub const SYSNO_WRITE: usize = 1;
 
+/// number of the system call `open`
+pub const SYSNO_OPEN: usize = 2;
+
 /// number of the system call `close`
 pub const SYSNO_CLOSE: usize = 3;
 
+/// number of the system call `lseek`
+pub const SYSNO_LSEEK: usize = 8;
+
+/// number of the system call `mmap`
+pub const SYSNO_MMAP: usize = 9;
+
+/// number of the system call `mprotect`
+pub const SYSNO_MPROTECT: usize = 10;
+
+/// number of the system call `munmap`
+pub const SYSNO_MUNMAP: usize = 11;
+
+/// number of the system call `brk`
+pub const SYSNO_BRK: usize = 12;
+
 pub const SYSNO_IOCTL: usize = 16;
 
+/// number of the system call `pread64`
+pub const SYSNO_PREAD64: usize = 17;
+
+/// number of the system call `pwrite64`
+pub const SYSNO_PWRITE64: usize = 18;
+
+/// number of the system call `readv`
+pub const SYSNO_READV: usize = 19;
+
 pub const SYSNO_WRITEV: usize = 20;
 
+/// number of the system call `mremap`
+pub const SYSNO_MREMAP: usize = 25;
+
+/// number of the system call `nanosleep`
+pub const SYSNO_NANOSLEEP: usize = 35;
+
+/// number of the system call `clone`
+pub const SYSNO_CLONE: usize = 56;
+
+/// number of the system call `fork`
+pub const SYSNO_FORK: usize = 57;
+
+/// number of the system call `execve`
+pub const SYSNO_EXECVE: usize = 59;
+
 /// number of the system call `exit`
 pub const SYSNO_EXIT: usize = 60;
 
+/// number of the system call `gettimeofday`
+pub const SYSNO_GETTIMEOFDAY: usize = 96;
+
+/// number of the system call `getrlimit`
+pub const SYSNO_GETRLIMIT: usize = 97;
+
+/// number of the system call `arch_prctl`
 pub const SYSNO_ARCH_PRCTL: usize = 158;
 
+/// number of the system call `setrlimit`
+pub const SYSNO_SETRLIMIT: usize = 160;
+
+/// number of the system call `time`
+pub const SYSNO_TIME: usize = 201;
+
 /// set pointer to thread ID
 pub const SYSNO_SET_TID_ADDRESS: usize = 218;
 
+/// number of the system call `clock_gettime`
+pub const SYSNO_CLOCK_GETTIME: usize = 228;
+
+/// number of the system call `clock_nanosleep`
+pub const SYSNO_CLOCK_NANOSLEEP: usize = 230;
+
 /// exit all threads in a process
 pub const SYSNO_EXIT_GROUP: usize = 231;
 
+/// number of the system call `prlimit64`
+pub const SYSNO_PRLIMIT64: usize = 302;
+
 /// total number of system calls
 pub const NO_SYSCALLS: usize = 400;
 
+/// Size of the kernel buffer, which is used to copy file data from and to the user space
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Convert the result of a system call to the Linux convention. In case
+/// of an error, the system call returns the negative error number.
+fn syscall_result(result: Result<usize>) -> isize {
+	match result {
+		Ok(value) => value as isize,
+		Err(e) => -e.errno() as isize
+	}
+}
+
 #[repr(align(64))]
 #[repr(C)]
 pub struct SyscallTable{
@@ -51,14 +155,36 @@ impl SyscallTable {
 			handle:	[sys_invalid as *const _; NO_SYSCALLS]
 		};
 
+		table.handle[SYSNO_READ] = sys_read as *const _;
 		table.handle[SYSNO_WRITE] = sys_write as *const _;
-		table.handle[SYSNO_CLOSE] = sys_nothing as *const _;
+		table.handle[SYSNO_OPEN] = sys_open as *const _;
+		table.handle[SYSNO_CLOSE] = sys_close as *const _;
+		table.handle[SYSNO_LSEEK] = sys_lseek as *const _;
+		table.handle[SYSNO_MMAP] = sys_mmap as *const _;
+		table.handle[SYSNO_MPROTECT] = sys_mprotect as *const _;
+		table.handle[SYSNO_MUNMAP] = sys_munmap as *const _;
+		table.handle[SYSNO_BRK] = sys_brk as *const _;
 		table.handle[SYSNO_IOCTL] = sys_nothing as *const _;
+		table.handle[SYSNO_PREAD64] = sys_pread64 as *const _;
+		table.handle[SYSNO_PWRITE64] = sys_pwrite64 as *const _;
+		table.handle[SYSNO_READV] = sys_readv as *const _;
 		table.handle[SYSNO_WRITEV] = sys_writev as *const _;
+		table.handle[SYSNO_MREMAP] = sys_mremap as *const _;
+		table.handle[SYSNO_NANOSLEEP] = sys_nanosleep as *const _;
+		table.handle[SYSNO_CLONE] = sys_clone as *const _;
+		table.handle[SYSNO_FORK] = sys_fork as *const _;
+		table.handle[SYSNO_EXECVE] = sys_execve as *const _;
 		table.handle[SYSNO_EXIT] = sys_exit as *const _;
-		table.handle[SYSNO_ARCH_PRCTL] = sys_nothing as *const _;
+		table.handle[SYSNO_GETTIMEOFDAY] = sys_gettimeofday as *const _;
+		table.handle[SYSNO_GETRLIMIT] = sys_getrlimit as *const _;
+		table.handle[SYSNO_ARCH_PRCTL] = sys_arch_prctl as *const _;
+		table.handle[SYSNO_SETRLIMIT] = sys_setrlimit as *const _;
+		table.handle[SYSNO_TIME] = sys_time as *const _;
 		table.handle[SYSNO_SET_TID_ADDRESS] = sys_nothing as *const _;
+		table.handle[SYSNO_CLOCK_GETTIME] = sys_clock_gettime as *const _;
+		table.handle[SYSNO_CLOCK_NANOSLEEP] = sys_clock_nanosleep as *const _;
 		table.handle[SYSNO_EXIT_GROUP] = sys_exit as *const _;
+		table.handle[SYSNO_PRLIMIT64] = sys_prlimit64 as *const _;
 
 		table
 	}
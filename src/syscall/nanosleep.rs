@@ -0,0 +1,61 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use core::time::Duration;
+use mm::user::get_user;
+use time::*;
+use scheduler;
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// `request` is an absolute time value
+const TIMER_ABSTIME: i32 = 1;
+
+fn sleep_ns(ns: u64) {
+	scheduler::sleep(Duration::new(ns / NSEC_PER_SEC, (ns % NSEC_PER_SEC) as u32));
+}
+
+/// Suspend the current task until `request` is elapsed or, with
+/// `TIMER_ABSTIME`, reached. The sleep can't be interrupted by a
+/// signal and, consequently, the remaining time isn't stored.
+fn clock_nanosleep(clock: i32, flags: i32, request: usize) -> Result<()> {
+	let ns = get_user::<Timespec>(request)?.to_ns()?;
+
+	let now = match clock {
+		CLOCK_REALTIME => realtime_ns(),
+		CLOCK_MONOTONIC | CLOCK_BOOTTIME => monotonic_ns(),
+		_ => {
+			error!("Unsupported clock {} (flags 0x{:x})", clock, flags);
+			return Err(Error::InvalidArgument);
+		}
+	};
+
+	if flags & TIMER_ABSTIME != 0 {
+		if ns > now {
+			sleep_ns(ns - now);
+		}
+	} else {
+		sleep_ns(ns);
+	}
+
+	Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn sys_nanosleep(request: usize, _remain: usize) -> isize
+{
+	syscall_result(clock_nanosleep(CLOCK_MONOTONIC, 0, request).map(|_| 0))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_clock_nanosleep(clock: i32, flags: i32, request: usize, _remain: usize) -> isize
+{
+	debug!("clock_nanosleep({}, 0x{:x}, 0x{:x})", clock, flags, request);
+
+	syscall_result(clock_nanosleep(clock, flags, request).map(|_| 0))
+}
@@ -7,6 +7,6 @@
 
 
 #[no_mangle]
-pub extern "C" fn sys_nothing() -> i32 {
+pub extern "C" fn sys_nothing() -> isize {
 	0
 }
@@ -0,0 +1,48 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use fs;
+use fs::OpenOptions;
+use scheduler;
+use mm::user::copy_string_from_user;
+use syscall::syscall_result;
+use errno::*;
+use logging::*;
+
+/// Maximum length of a path
+const PATH_MAX: usize = 4096;
+
+/// mask of the access mode
+const O_ACCMODE: i32 = 0o3;
+/// open for reading only
+const O_RDONLY: i32 = 0o0;
+/// create the file, if it doesn't exist
+const O_CREAT: i32 = 0o100;
+
+fn open(path: usize, flags: i32) -> Result<i32> {
+	let path = copy_string_from_user(path, PATH_MAX, Error::NameTooLong)?;
+	let mut options = if flags & O_ACCMODE == O_RDONLY {
+		OpenOptions::READONLY
+	} else {
+		OpenOptions::READWRITE
+	};
+
+	if flags & O_CREAT != 0 {
+		options.insert(OpenOptions::CREATE);
+	}
+
+	debug!("Open file {} (flags 0x{:x})", path, flags);
+
+	let file = fs::open(&path, options)?;
+	scheduler::insert_file(file)
+}
+
+#[no_mangle]
+pub extern "C" fn sys_open(path: usize, flags: i32, _mode: i32) -> isize
+{
+	syscall_result(open(path, flags).map(|fd| fd as usize))
+}
@@ -0,0 +1,101 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use alloc::vec::Vec;
+use core::cmp;
+use fs::{FileHandle,SeekFrom};
+use mm::user::UserSlice;
+use scheduler;
+use syscall::{syscall_result,IO_BUFFER_SIZE};
+use syscall::write::get_iovecs;
+use errno::*;
+
+/// Read from `file` into the user-space buffer `buf`
+fn read_file(file: &mut FileHandle, buf: UserSlice) -> Result<usize> {
+	let mut chunk: Vec<u8> = Vec::new();
+	let mut total: usize = 0;
+
+	chunk.resize(cmp::min(buf.len(), IO_BUFFER_SIZE), 0);
+
+	while total < buf.len() {
+		let len = cmp::min(chunk.len(), buf.len() - total);
+		let n = file.read(&mut chunk[..len])?;
+
+		buf.subslice(total, n).write(&chunk[..n])?;
+		total += n;
+
+		// end of file
+		if n < len {
+			break;
+		}
+	}
+
+	Ok(total)
+}
+
+fn read(fd: i32, buf: UserSlice) -> Result<usize> {
+	let file = scheduler::get_file(fd)?;
+	let result = read_file(&mut **file.lock(), buf);
+	result
+}
+
+/// Read from the position `offset` without changing the file position
+fn pread(fd: i32, buf: UserSlice, offset: i64) -> Result<usize> {
+	if offset < 0 {
+		return Err(Error::InvalidArgument);
+	}
+
+	let file = scheduler::get_file(fd)?;
+	let mut file = file.lock();
+	let pos = file.seek(SeekFrom::Current(0))?;
+
+	file.seek(SeekFrom::Start(offset as u64))?;
+	let result = read_file(&mut **file, buf);
+	file.seek(SeekFrom::Start(pos))?;
+
+	result
+}
+
+/// Read into the buffers one after another. After a short read (e.g. at the
+/// end of the file) or an error, the number of bytes, which are already
+/// read, is returned.
+fn readv(fd: i32, iov: usize, cnt: i32) -> Result<usize> {
+	let mut len: usize = 0;
+
+	for buf in get_iovecs(iov, cnt)? {
+		match read(fd, buf) {
+			Ok(n) => {
+				len += n;
+				if n < buf.len() {
+					break;
+				}
+			},
+			Err(e) if len == 0 => return Err(e),
+			Err(_) => break
+		}
+	}
+
+	Ok(len)
+}
+
+#[no_mangle]
+pub extern "C" fn sys_read(fd: i32, buf: usize, len: usize) -> isize
+{
+	syscall_result(UserSlice::new(buf, len).and_then(|buf| read(fd, buf)))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_pread64(fd: i32, buf: usize, len: usize, offset: i64) -> isize
+{
+	syscall_result(UserSlice::new(buf, len).and_then(|buf| pread(fd, buf, offset)))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_readv(fd: i32, iov: usize, cnt: i32) -> isize
+{
+	syscall_result(readv(fd, iov, cnt))
+}
@@ -0,0 +1,107 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use consts::*;
+use mm::user::{get_user,put_user};
+use scheduler;
+use errno::*;
+use logging::*;
+use syscall::syscall_result;
+
+/// maximum size of the stack
+const RLIMIT_STACK: u32 = 3;
+/// maximum number of open files
+const RLIMIT_NOFILE: u32 = 7;
+/// number of resources
+const RLIM_NLIMITS: u32 = 16;
+/// the resource isn't limited
+const RLIM_INFINITY: u64 = !0;
+
+/// Soft and hard limit of a resource, which is identical to `struct rlimit`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct RLimit {
+	cur: u64,
+	max: u64
+}
+
+fn get_limit(resource: u32) -> Result<RLimit> {
+	match resource {
+		RLIMIT_STACK => {
+			let (cur, max) = scheduler::get_stack_limit();
+			Ok(RLimit { cur: cur as u64, max: max as u64 })
+		},
+		RLIMIT_NOFILE => Ok(RLimit { cur: MAX_FILE_DESCRIPTORS as u64, max: MAX_FILE_DESCRIPTORS as u64 }),
+		_ if resource < RLIM_NLIMITS => Ok(RLimit { cur: RLIM_INFINITY, max: RLIM_INFINITY }),
+		_ => Err(Error::InvalidArgument)
+	}
+}
+
+fn set_limit(resource: u32, limit: RLimit) -> Result<()> {
+	let old = get_limit(resource)?;
+
+	if limit.cur > limit.max {
+		return Err(Error::InvalidArgument);
+	}
+	if limit.max > old.max {
+		return Err(Error::NotPermitted);
+	}
+
+	match resource {
+		RLIMIT_STACK => scheduler::set_stack_limit(limit.cur as usize, limit.max as usize),
+		_ => {
+			// the other limits are fixed => lower limits are ignored
+			debug!("Ignore limit {:?} of resource {}", limit, resource);
+			Ok(())
+		}
+	}
+}
+
+/// Get and set the limits of the current task. The previous limits are
+/// stored at `old_limit`, before `new_limit` is applied.
+fn prlimit(pid: i32, resource: u32, new_limit: usize, old_limit: usize) -> Result<()> {
+	if pid != 0 && pid as u32 != scheduler::get_current_taskid().into() {
+		error!("prlimit64 supports only the current task");
+		return Err(Error::NotPermitted);
+	}
+
+	let limit = if new_limit != 0 {
+		Some(get_user::<RLimit>(new_limit)?)
+	} else {
+		None
+	};
+
+	if old_limit != 0 {
+		put_user(old_limit, get_limit(resource)?)?;
+	}
+
+	if let Some(limit) = limit {
+		set_limit(resource, limit)?;
+	}
+
+	Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn sys_getrlimit(resource: u32, limit: usize) -> isize
+{
+	syscall_result(prlimit(0, resource, 0, limit).map(|_| 0))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_setrlimit(resource: u32, limit: usize) -> isize
+{
+	syscall_result(prlimit(0, resource, limit, 0).map(|_| 0))
+}
+
+#[no_mangle]
+pub extern "C" fn sys_prlimit64(pid: i32, resource: u32, new_limit: usize, old_limit: usize) -> isize
+{
+	debug!("prlimit64({}, {}, 0x{:x}, 0x{:x})", pid, resource, new_limit, old_limit);
+
+	syscall_result(prlimit(pid, resource, new_limit, old_limit).map(|_| 0))
+}
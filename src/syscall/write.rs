@@ -5,36 +5,122 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp;
+use core::mem::size_of;
+use fs::{FileHandle,SeekFrom};
+use mm::user::{UserSlice,get_user};
+use scheduler;
+use syscall::{syscall_result,IO_BUFFER_SIZE};
+use errno::*;
+
+/// Maximum number of buffers, which are passed to readv and writev
+const IOV_MAX: i32 = 1024;
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct IoVec {
-	pub iov_base: *const u8,
+	pub iov_base: usize,
 	pub iov_len: usize
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn sys_writev(_fd: i32, ptr: *const IoVec, cnt: i32) -> isize
-{
-	let mut len: isize = 0;
-	let iovec = core::slice::from_raw_parts(ptr, cnt as usize);
+/// Copy the vector of `cnt` buffers at `iov` into the kernel. Like Linux,
+/// all buffers are checked before the first one is transferred.
+pub fn get_iovecs(iov: usize, cnt: i32) -> Result<Vec<UserSlice>> {
+	if cnt < 0 || cnt > IOV_MAX {
+		return Err(Error::InvalidArgument);
+	}
+
+	let mut slices = Vec::with_capacity(cnt as usize);
+	for i in 0..cnt as usize {
+		let vec: IoVec = get_user(iov + i * size_of::<IoVec>())?;
+
+		slices.push(UserSlice::new(vec.iov_base, vec.iov_len)?);
+	}
+
+	Ok(slices)
+}
+
+/// Write the user-space buffer `buf` to `file`
+fn write_file(file: &mut FileHandle, buf: UserSlice) -> Result<usize> {
+	let mut chunk: Vec<u8> = Vec::new();
+	let mut total: usize = 0;
+
+	chunk.resize(cmp::min(buf.len(), IO_BUFFER_SIZE), 0);
+
+	while total < buf.len() {
+		let len = cmp::min(chunk.len(), buf.len() - total);
 
-	for i in iovec {
-		let s = core::slice::from_raw_parts(i.iov_base, i.iov_len);
+		buf.subslice(total, len).read(&mut chunk[..len])?;
+		let n = file.write(&chunk[..len])?;
+		total += n;
 
-		len += i.iov_len as isize;
-		print!("{}", String::from_utf8_lossy(s));
+		if n < len {
+			break;
+		}
 	}
 
-	len
+	Ok(total)
+}
+
+fn write(fd: i32, buf: UserSlice) -> Result<usize> {
+	let file = scheduler::get_file(fd)?;
+	let result = write_file(&mut **file.lock(), buf);
+	result
+}
+
+/// Write at the position `offset` without changing the file position
+fn pwrite(fd: i32, buf: UserSlice, offset: i64) -> Result<usize> {
+	if offset < 0 {
+		return Err(Error::InvalidArgument);
+	}
+
+	let file = scheduler::get_file(fd)?;
+	let mut file = file.lock();
+	let pos = file.seek(SeekFrom::Current(0))?;
+
+	file.seek(SeekFrom::Start(offset as u64))?;
+	let result = write_file(&mut **file, buf);
+	file.seek(SeekFrom::Start(pos))?;
+
+	result
+}
+
+/// Write the buffers one after another. After a short write or an error,
+/// the number of bytes, which are already written, is returned.
+fn writev(fd: i32, iov: usize, cnt: i32) -> Result<usize> {
+	let mut len: usize = 0;
+
+	for buf in get_iovecs(iov, cnt)? {
+		match write(fd, buf) {
+			Ok(n) => {
+				len += n;
+				if n < buf.len() {
+					break;
+				}
+			},
+			Err(e) if len == 0 => return Err(e),
+			Err(_) => break
+		}
+	}
+
+	Ok(len)
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn sys_write(_fd: i32, s: *const u8, len: usize) -> isize
+pub extern "C" fn sys_writev(fd: i32, iov: usize, cnt: i32) -> isize
 {
-	let text = core::slice::from_raw_parts(s, len);
+	syscall_result(writev(fd, iov, cnt))
+}
 
-	print!("{}", String::from_utf8_lossy(text));
+#[no_mangle]
+pub extern "C" fn sys_write(fd: i32, buf: usize, len: usize) -> isize
+{
+	syscall_result(UserSlice::new(buf, len).and_then(|buf| write(fd, buf)))
+}
 
-	len as isize
+#[no_mangle]
+pub extern "C" fn sys_pwrite64(fd: i32, buf: usize, len: usize, offset: i64) -> isize
+{
+	syscall_result(UserSlice::new(buf, len).and_then(|buf| pwrite(fd, buf, offset)))
 }
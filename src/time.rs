@@ -0,0 +1,109 @@
+// Copyright (c) 2019 Stefan Lankes, RWTH Aachen University
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Clocks of the kernel
+//!
+//! The timer interrupt increments the number of jiffies `TIMER_FREQ` times
+//! per second. The monotonic clock starts at boot time and is measured by
+//! the time stamp counter. Without a calibrated counter, it has the
+//! resolution of one jiffy. The real time is derived from the wall-clock
+//! time at boot time.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use arch;
+use consts::*;
+use errno::*;
+
+/// Nanoseconds per second
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+/// Nanoseconds between two timer interrupts
+pub const NSEC_PER_JIFFY: u64 = NSEC_PER_SEC / TIMER_FREQ as u64;
+
+/// system-wide real time clock
+pub const CLOCK_REALTIME: i32 = 0;
+/// monotonic clock, which starts at boot time
+pub const CLOCK_MONOTONIC: i32 = 1;
+/// CPU time of the process
+pub const CLOCK_PROCESS_CPUTIME_ID: i32 = 2;
+/// CPU time of the thread
+pub const CLOCK_THREAD_CPUTIME_ID: i32 = 3;
+/// monotonic clock without NTP adjustments
+pub const CLOCK_MONOTONIC_RAW: i32 = 4;
+/// faster, but less precise real time clock
+pub const CLOCK_REALTIME_COARSE: i32 = 5;
+/// faster, but less precise monotonic clock
+pub const CLOCK_MONOTONIC_COARSE: i32 = 6;
+/// monotonic clock, which includes suspended time
+pub const CLOCK_BOOTTIME: i32 = 7;
+
+/// Number of timer interrupts since boot time
+static JIFFIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Time value of the system calls, which is identical to `struct timespec`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Timespec {
+	pub tv_sec: i64,
+	pub tv_nsec: i64
+}
+
+impl Timespec {
+	/// Create the time value of `ns` nanoseconds
+	pub fn from_ns(ns: u64) -> Self {
+		Timespec {
+			tv_sec: (ns / NSEC_PER_SEC) as i64,
+			tv_nsec: (ns % NSEC_PER_SEC) as i64
+		}
+	}
+
+	/// Returns the time value in nanoseconds
+	pub fn to_ns(&self) -> Result<u64> {
+		if self.tv_sec < 0 || self.tv_nsec < 0 || self.tv_nsec >= NSEC_PER_SEC as i64 {
+			return Err(Error::InvalidArgument);
+		}
+
+		(self.tv_sec as u64).checked_mul(NSEC_PER_SEC)
+			.and_then(|ns| ns.checked_add(self.tv_nsec as u64))
+			.ok_or(Error::InvalidArgument)
+	}
+}
+
+/// Count a timer interrupt, must be called only by the timer handler
+pub fn tick() {
+	JIFFIES.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Returns the number of timer interrupts since boot time
+pub fn jiffies() -> u64 {
+	JIFFIES.load(Ordering::SeqCst) as u64
+}
+
+/// Returns the nanoseconds since boot time
+pub fn monotonic_ns() -> u64 {
+	arch::get_monotonic_ns().unwrap_or_else(|| jiffies() * NSEC_PER_JIFFY)
+}
+
+/// Returns the nanoseconds since the epoch
+pub fn realtime_ns() -> u64 {
+	arch::get_boot_time() * NSEC_PER_SEC + monotonic_ns()
+}
+
+/// Returns the point of time in nanoseconds, which lies `duration` in the
+/// future. Without the time stamp counter, the current jiffy is already
+/// partially elapsed and, consequently, one jiffy is added to wait at least
+/// `duration`.
+pub fn deadline(duration: Duration) -> u64 {
+	let ns = duration.as_secs().saturating_mul(NSEC_PER_SEC)
+		.saturating_add(duration.subsec_nanos() as u64);
+
+	match arch::get_monotonic_ns() {
+		Some(now) => now.saturating_add(ns),
+		None => (jiffies() * NSEC_PER_JIFFY).saturating_add(ns).saturating_add(NSEC_PER_JIFFY)
+	}
+}